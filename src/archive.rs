@@ -0,0 +1,165 @@
+//! # Archive Module for RustCat
+//!
+//! This module reads members out of tar and zip archives, so that operands such as
+//! `bundle.tar.gz::path/in/archive` or `release.zip::config.yaml` display a single member,
+//! and lists the members of archives for `--list-archive`.
+//!
+//! The archive format is detected from the content of the file. Tar archives may be compressed
+//! with any format supported by the `decompress` module.
+use crate::decompress;
+use std::fs::File;
+use std::io::{self, Cursor, Read};
+use std::path::Path;
+
+/// The separator between the path of an archive and the name of a member in an operand.
+const MEMBER_SEPARATOR: &str = "::";
+/// The first bytes of a zip archive, or of an empty one.
+const ZIP_MAGIC: [&[u8]; 2] = [b"PK\x03\x04", b"PK\x05\x06"];
+
+/// Splits an operand such as `bundle.tar.gz::path/in/archive` into the archive and member names.
+///
+/// Returns `None` if the operand does not name a member, including when it is the name of an
+/// existing file that happens to contain the separator.
+pub fn split_operand(operand: &str) -> Option<(&str, &str)> {
+    let (archive, member) = operand.split_once(MEMBER_SEPARATOR)?;
+    if Path::new(operand).exists() || archive.is_empty() || member.is_empty() {
+        return None;
+    }
+    Some((archive, member))
+}
+
+/// Returns the name that describes the content of an operand, the member name for archive members.
+///
+/// This is used to detect the syntax of the content.
+pub fn content_name(operand: &str) -> &str {
+    split_operand(operand).map_or(operand, |(_, member)| member)
+}
+
+/// An opened tar or zip archive.
+enum Archive {
+    /// A tar archive, read from its possibly decoded content.
+    Tar(tar::Archive<Box<dyn Read>>),
+    /// A zip archive, which is read through its central directory.
+    Zip(zip::ZipArchive<File>),
+}
+
+impl Archive {
+    /// Opens an archive, detecting its format from its content.
+    fn open(filename: &str) -> io::Result<Self> {
+        let mut file = File::open(filename)?;
+        let mut magic = [0; 4];
+        let magic_len = file.read(&mut magic)?;
+        if ZIP_MAGIC.contains(&&magic[..magic_len]) {
+            let zip_archive = zip::ZipArchive::new(File::open(filename)?).map_err(zip_error)?;
+            return Ok(Archive::Zip(zip_archive));
+        }
+        // Tar archives are usually compressed, as in `.tar.gz`
+        let reader = match decompress::open_decompressed(filename)? {
+            Some(reader) => reader,
+            None => Box::new(File::open(filename)?),
+        };
+        Ok(Archive::Tar(tar::Archive::new(reader)))
+    }
+}
+
+/// Converts an error of the `zip` crate into an I/O error.
+fn zip_error(error: zip::result::ZipError) -> io::Error {
+    match error {
+        zip::result::ZipError::Io(error) => error,
+        zip::result::ZipError::FileNotFound => no_such_member(),
+        error => io::Error::new(io::ErrorKind::InvalidData, error.to_string()),
+    }
+}
+
+/// Converts an error of the `tar` crate into an I/O error.
+///
+/// Headers that cannot be parsed are reported as a file that is not an archive at all.
+fn tar_error(error: io::Error) -> io::Error {
+    match error.kind() {
+        io::ErrorKind::Other => {
+            io::Error::new(io::ErrorKind::InvalidData, "Not a tar or zip archive")
+        }
+        _ => error,
+    }
+}
+
+/// The error returned for members that are not in the archive.
+fn no_such_member() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "No such member in archive")
+}
+
+/// Normalizes a member name, ignoring a leading `./` as tar archives often store one.
+fn normalize_member_name(name: &str) -> &str {
+    name.trim_start_matches("./")
+}
+
+/// Reads the content of a member of a tar or zip archive.
+///
+/// The content is returned as a reader so that it can be read like a named pipe.
+///
+/// # Examples
+///
+/// ```
+/// let mut reader = read_member("release.zip", "config.yaml")?;
+/// io::copy(&mut reader, &mut io::stdout())?;
+/// ```
+pub fn read_member(filename: &str, member: &str) -> io::Result<Box<dyn Read>> {
+    let member = normalize_member_name(member);
+    let mut content = Vec::new();
+
+    match Archive::open(filename)? {
+        Archive::Tar(mut tar_archive) => {
+            let mut entries = tar_archive.entries()?;
+            // Members are stored one after the other, so the archive is read up to the member
+            let mut entry = loop {
+                let entry = entries
+                    .next()
+                    .ok_or_else(no_such_member)?
+                    .map_err(tar_error)?;
+                if normalize_member_name(&entry.path()?.to_string_lossy()).trim_end_matches('/')
+                    == member.trim_end_matches('/')
+                {
+                    break entry;
+                }
+            };
+            if entry.header().entry_type().is_dir() {
+                return Err(io::Error::from(io::ErrorKind::IsADirectory));
+            }
+            entry.read_to_end(&mut content)?;
+        }
+        Archive::Zip(mut zip_archive) => {
+            let mut file = zip_archive.by_name(member).map_err(zip_error)?;
+            if file.is_dir() {
+                return Err(io::Error::from(io::ErrorKind::IsADirectory));
+            }
+            file.read_to_end(&mut content)?;
+        }
+    }
+    Ok(Box::new(Cursor::new(content)))
+}
+
+/// Lists the names of the members of a tar or zip archive, in the order they are stored in.
+pub fn list_members(filename: &str) -> io::Result<Vec<String>> {
+    let mut members = Vec::new();
+
+    match Archive::open(filename)? {
+        Archive::Tar(mut tar_archive) => {
+            for entry in tar_archive.entries()? {
+                let entry = entry.map_err(tar_error)?;
+                let mut name = entry.path()?.to_string_lossy().into_owned();
+                // Mark directories like zip archives do
+                if entry.header().entry_type().is_dir() && !name.ends_with('/') {
+                    name.push('/');
+                }
+                members.push(name);
+            }
+        }
+        Archive::Zip(mut zip_archive) => {
+            for index in 0..zip_archive.len() {
+                let file = zip_archive.by_index(index).map_err(zip_error)?;
+                members.push(file.name().to_string());
+            }
+        }
+    }
+    Ok(members)
+}
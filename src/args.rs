@@ -7,7 +7,11 @@
 //! The `Cli` struct is also responsible for handling combined or conflicting command-line options and setting
 //! appropriate flags for other parts of the application to use.
 
-use clap::Parser;
+use crate::selection::SelectionRange;
+use crate::tabs::TabStops;
+use clap::{Parser, ValueEnum};
+use regex::Regex;
+use std::str::FromStr;
 /// Command-line arguments structure for RustCat.
 ///
 /// This structure defines all the possible command-line options that can be passed to RustCat.
@@ -17,28 +21,111 @@ use clap::Parser;
 ///
 /// * `files` - Files to display.
 /// * `search` - Optional search term for highlighting within the file content.
+/// * `word_regexp` - Flag to only match the search term as a whole word.
+/// * `only_matching` - Flag to print only the matched parts of matching lines.
+/// * `show_column` - Flag to print the byte column of each match with `only_matching`.
+/// * `fuzzy` - Flag to match the search term fuzzily, as a subsequence of each line.
+/// * `fuzzy_threshold` - Minimum score, in percent, of the lines displayed by a fuzzy search.
+/// * `with_filename` - Flag to prefix each line with the name of its file.
+/// * `files_with_matches` - Flag to only list the files that contain the search term.
+/// * `files_without_match` - Flag to only list the files that do not contain the search term.
 /// * `show_line_numbers` - Flag to display line numbers.
 /// * `show_non_blank_line_numbers` - Flag to display line numbers for non-blank lines only.
 /// * `show_ends` - Flag to show `$` at the end of each line.
 /// * `show_nonprinting` - Flag to display non-printing characters.
-/// * `squeeze_blank` - Flag to suppress consecutive blank lines.
+/// * `squeeze_blank` - Optional number of consecutive blank lines kept, the others being suppressed.
 /// * `show_tabs` - Flag to display tabs as `^I`.
 /// * `show_nonprinting_and_ends` - Combined flag for non-printing characters and line end symbol.
 /// * `show_nonprinting_and_tabs` - Combined flag for non-printing characters and tab symbol.
 /// * `show_all` - Flag to enable all display options.
 /// * `highlight_syntax` - Flag to enable syntax highlighting.
+/// * `lines` - Ranges of lines to display from each file.
+/// * `bytes` - Range of bytes to display from each file.
+/// * `head` - Number of lines to display from the start of each file.
+/// * `tail` - Number of lines to display from the end of each file.
+/// * `show_byte_offset` - Flag to display the byte offset of each line instead of its line number.
+/// * `follow` - Flag to keep displaying the data appended to the files.
+/// * `timestamp` - Optional format of the timestamp prefixed to each line, the default one if empty.
+/// * `timestamp_relative` - Flag to timestamp lines with the time elapsed since the start.
+/// * `decompress` - Flag to decode the files compressed with gzip, zstd, bzip2 or xz.
+/// * `list_archive` - Flag to list the members of tar and zip archives instead of displaying them.
+/// * `recursive` - Flag to display the regular files of directories, walked recursively.
+/// * `include` - Globs selecting the files displayed from directories.
+/// * `exclude` - Globs of the files and directories skipped in directories.
+/// * `skip_binary` - Flag to skip the binary files found in directories.
+/// * `no_ignore` - Flag to also display the files of directories that ignore files exclude.
+/// * `hidden` - Flag to also display the hidden files and directories of directories.
+/// * `header` - Flag to print a header with the name, size and language of each file before its content.
+/// * `separator` - Optional line printed between the files.
+/// * `number_per_file` - Flag to restart the line numbers at the starting line number for each file.
+/// * `decorations` - When to print the headers and separators.
+/// * `files_from` - Optional file listing more files to display, `-` for standard input.
+/// * `null` - Flag to separate the names in `files_from` with NUL bytes instead of newlines.
+/// * `whitespace_is_blank` - Flag to treat lines with only whitespace as blank lines.
+/// * `eol` - Line ending of the output lines, or `keep` to output the line endings of the files.
+/// * `null_data` - Flag to separate the records of the files with NUL bytes instead of newlines.
+/// * `record_separator` - Optional byte separating the records of the files instead of newlines.
+/// * `ensure_newline` - Flag to end the output of each file with a newline if the file lacks one.
+/// * `mark_missing_newline` - Flag to print a marker line after files that lack a trailing newline.
+/// * `tabs` - Optional tab stops to expand tabs to spaces.
+/// * `tab_arrow` - Flag to start expanded tabs with an arrow.
+/// * `show_whitespace` - Flag to display spaces and Unicode spaces as visible glyphs and highlight trailing whitespace.
+/// * `show_unicode` - Flag to display invisible and bidirectional Unicode characters, and homoglyphs, as their code points.
+/// * `check_bidi` - Flag to report bidirectional control characters and exit with an error if there are any.
+/// * `nonprinting_style` - Notation of the characters shown by `-v`, `-T` and `-E`.
+/// * `number_style` - Optional choice of the lines to number, like the `-b` option of `nl`.
+/// * `number_width` - Width of the line numbers.
+/// * `number_format` - Alignment of the line numbers within their width.
+/// * `number_separator` - String printed between the line numbers and the lines.
+/// * `starting_line_number` - First line number of the output.
+/// * `line_increment` - Amount added to the line number for each numbered line.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 #[clap(version = env!("CARGO_PKG_VERSION"))]
 pub struct Cli {
     /// Field Definitions
     /// ------------------
-    /// Files to display
-    #[arg(required = true)]
+    /// Files to display, or archive members such as `bundle.tar.gz::path/in/archive`
+    #[arg(required_unless_present = "files_from")]
     pub files: Vec<String>,
     /// Optional search term for highlighting within the file content.
     #[arg(short = 'f', long = "search")]
     pub search: Option<String>,
+    /// Flag to only match the search term as a whole word.
+    #[arg(short = 'w', long = "word-regexp", requires = "search")]
+    pub word_regexp: bool,
+    /// Flag to print only the matched parts of matching lines, one per line.
+    #[arg(short = 'o', long = "only-matching", requires = "search")]
+    pub only_matching: bool,
+    /// Flag to print the byte column of each match with `--only-matching`.
+    #[arg(long = "column", requires = "only_matching")]
+    pub show_column: bool,
+    /// Flag to match the search term fuzzily, as a subsequence of each line.
+    #[arg(long = "fuzzy", requires = "search", conflicts_with = "word_regexp")]
+    pub fuzzy: bool,
+    /// Minimum score, in percent, of the lines displayed by a fuzzy search.
+    #[arg(
+        long = "fuzzy-threshold",
+        value_name = "PERCENT",
+        default_value_t = 50,
+        value_parser = clap::value_parser!(u8).range(0..=100),
+        requires = "fuzzy"
+    )]
+    pub fuzzy_threshold: u8,
+    /// Flag to prefix each line with the name of its file.
+    #[arg(short = 'H', long = "with-filename")]
+    pub with_filename: bool,
+    /// Flag to only list the files that contain the search term.
+    #[arg(short = 'l', long = "files-with-matches", requires = "search")]
+    pub files_with_matches: bool,
+    /// Flag to only list the files that do not contain the search term.
+    #[arg(
+        short = 'L',
+        long = "files-without-match",
+        requires = "search",
+        conflicts_with = "files_with_matches"
+    )]
+    pub files_without_match: bool,
     /// Flag to display line numbers.
     #[arg(short = 'n', long = "number")]
     pub show_line_numbers: bool,
@@ -51,9 +138,16 @@ pub struct Cli {
     /// Flag to display non-printing characters.
     #[arg(short = 'v', long = "show-nonprinting")]
     pub show_nonprinting: bool,
-    /// Flag to suppress consecutive blank lines.
-    #[arg(short = 's', long = "squeeze-blank")]
-    pub squeeze_blank: bool,
+    /// Suppress repeated blank lines, keeping at most N consecutive ones (1 by default).
+    #[arg(
+        short = 's',
+        long = "squeeze-blank",
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1"
+    )]
+    pub squeeze_blank: Option<usize>,
     /// Flag to display tabs as `^I`.
     #[arg(short = 'T', long = "show-tabs")]
     pub show_tabs: bool,
@@ -69,6 +163,155 @@ pub struct Cli {
     /// Flag to enable syntax highlighting.
     #[arg(short = 'x', long = "highlight-syntax")]
     pub highlight_syntax: bool,
+    /// Ranges of lines to display from each file, such as `10:20`, `:50`, `100:` or `-20:`
+    /// for the last 20 lines. Several ranges can be separated by commas.
+    #[arg(
+        long = "lines",
+        value_name = "START:END",
+        value_delimiter = ',',
+        allow_hyphen_values = true
+    )]
+    pub lines: Vec<SelectionRange>,
+    /// Range of bytes to display from each file, such as `512:1023` or `-256:` for the last
+    /// 256 bytes.
+    #[arg(long = "bytes", value_name = "START:END", allow_hyphen_values = true)]
+    pub bytes: Option<SelectionRange>,
+    /// Number of lines to display from the start of each file.
+    #[arg(long = "head", value_name = "N", conflicts_with_all = ["lines", "bytes", "tail"])]
+    pub head: Option<usize>,
+    /// Number of lines to display from the end of each file.
+    #[arg(long = "tail", value_name = "N", conflicts_with_all = ["lines", "bytes"])]
+    pub tail: Option<usize>,
+    /// Flag to display the byte offset of each line within its file instead of its line number.
+    #[arg(
+        long = "offset",
+        conflicts_with_all = ["show_line_numbers", "show_non_blank_line_numbers"]
+    )]
+    pub show_byte_offset: bool,
+    /// Flag to keep displaying the data appended to the files, until interrupted.
+    #[arg(
+        short = 'F',
+        long = "follow",
+        conflicts_with_all = ["head", "lines", "bytes", "files_with_matches", "files_without_match"]
+    )]
+    pub follow: bool,
+    /// Prefix each line with the time it was read, in the given strftime-style format.
+    #[arg(long = "timestamp", value_name = "FORMAT", num_args = 0..=1, require_equals = true)]
+    pub timestamp: Option<Option<String>>,
+    /// Flag to timestamp lines with the time elapsed since the start instead of the wall clock time.
+    #[arg(long = "timestamp-relative", requires = "timestamp")]
+    pub timestamp_relative: bool,
+    /// Flag to decode the files compressed with gzip, zstd, bzip2 or xz, detected from their content.
+    #[arg(short = 'z', long = "decompress", conflicts_with = "follow")]
+    pub decompress: bool,
+    /// Flag to list the members of tar and zip archives instead of displaying them.
+    #[arg(long = "list-archive", conflicts_with = "follow")]
+    pub list_archive: bool,
+    /// Flag to display the regular files of directories, walked recursively in sorted order.
+    #[arg(short = 'r', long = "recursive")]
+    pub recursive: bool,
+    /// Only display the files of directories whose path matches one of these globs.
+    #[arg(long = "include", value_name = "GLOB", requires = "recursive")]
+    pub include: Vec<String>,
+    /// Skip the files and directories whose path or name matches one of these globs.
+    #[arg(long = "exclude", value_name = "GLOB", requires = "recursive")]
+    pub exclude: Vec<String>,
+    /// Flag to skip the files of directories that contain NUL bytes near their start.
+    #[arg(long = "skip-binary", requires = "recursive")]
+    pub skip_binary: bool,
+    /// Flag to also display the files of directories excluded by `.gitignore`, `.ignore` or git excludes.
+    #[arg(long = "no-ignore", requires = "recursive")]
+    pub no_ignore: bool,
+    /// Flag to also display the hidden files and directories of directories.
+    #[arg(long = "hidden", requires = "recursive")]
+    pub hidden: bool,
+    /// Flag to print a header with the name, size and language of each file before its content.
+    #[arg(long = "header")]
+    pub header: bool,
+    /// Print STRING on its own line between the files.
+    #[arg(long = "separator", value_name = "STRING")]
+    pub separator: Option<String>,
+    /// Flag to restart the line numbers at the starting line number for each file.
+    #[arg(long = "number-per-file")]
+    pub number_per_file: bool,
+    /// When to print the headers and separators, by default only if the output is a terminal.
+    #[arg(long = "decorations", value_name = "WHEN", value_enum, default_value_t = Decorations::Auto)]
+    pub decorations: Decorations,
+    /// Read the names of more files to display from FILE, one per line, or from standard input if FILE is `-`.
+    #[arg(long = "files-from", value_name = "FILE")]
+    pub files_from: Option<String>,
+    /// Flag to separate the names read with `--files-from` with NUL bytes instead of newlines.
+    #[arg(long = "null", requires = "files_from")]
+    pub null: bool,
+    /// Flag to treat lines with only whitespace as blank when squeezing blank lines and numbering non-blank lines.
+    #[arg(long = "whitespace-is-blank")]
+    pub whitespace_is_blank: bool,
+    /// Line ending of the output lines: `keep` the ones of the files, or convert them all to `lf` or `crlf`.
+    #[arg(
+        long = "eol",
+        value_name = "EOL",
+        value_enum,
+        default_value_t = LineEnding::Keep,
+        conflicts_with_all = ["null_data", "record_separator"]
+    )]
+    pub eol: LineEnding,
+    /// Flag to split the files into records ending with NUL bytes instead of lines, like `sort -z`.
+    #[arg(short = '0', long = "null-data")]
+    pub null_data: bool,
+    /// Split the files into records ending with BYTE instead of lines, such as `;` or `\t`.
+    #[arg(
+        long = "record-separator",
+        value_name = "BYTE",
+        value_parser = parse_record_separator,
+        conflicts_with = "null_data"
+    )]
+    pub record_separator: Option<u8>,
+    /// Flag to end the output of each file with a newline if the file lacks one, so the next file starts on its own line.
+    #[arg(long = "ensure-newline")]
+    pub ensure_newline: bool,
+    /// Flag to print `\ No newline at end of file` after files that lack a trailing newline, like `diff`.
+    #[arg(long = "mark-missing-newline")]
+    pub mark_missing_newline: bool,
+    /// Expand tabs to spaces, with a tab stop every N columns or at a comma-separated list of columns.
+    #[arg(long = "tabs", value_name = "N|LIST")]
+    pub tabs: Option<TabStops>,
+    /// Flag to expand tabs to an arrow padded with spaces to the next tab stop, every 8 columns unless `--tabs` is given.
+    #[arg(long = "tab-arrow")]
+    pub tab_arrow: bool,
+    /// Flag to display spaces as `·`, no-break spaces as `⍽` and other Unicode spaces as `␣`, highlighting trailing whitespace.
+    #[arg(long = "show-whitespace")]
+    pub show_whitespace: bool,
+    /// Flag to display invisible and bidirectional control characters, such as zero-width spaces and U+202E, and Cyrillic or Greek letters that look like Latin ones in words mixing scripts, as `<U+202E>`.
+    #[arg(long = "show-unicode")]
+    pub show_unicode: bool,
+    /// Flag to report the bidirectional control characters of the files on stderr, and exit with status 1 if there are any.
+    #[arg(long = "check-bidi")]
+    pub check_bidi: bool,
+    /// Notation of the characters shown by `-v`, `-T` and `-E`: `caret` like GNU cat (`^[`), `picture` for control pictures (`␛`), `hex` (`\x1b`) or C-style `escape` (`\e`).
+    #[arg(long = "nonprinting-style", value_name = "STYLE", value_enum, default_value_t = NonprintingStyle::Caret)]
+    pub nonprinting_style: NonprintingStyle,
+    /// Number the lines selected by STYLE: `a` for all lines, `t` for non-blank lines or `pREGEX` for lines matching REGEX.
+    #[arg(
+        long = "number-style",
+        value_name = "STYLE",
+        conflicts_with = "show_byte_offset"
+    )]
+    pub number_style: Option<NumberStyle>,
+    /// Width of the line numbers.
+    #[arg(long = "number-width", value_name = "N", default_value_t = 6)]
+    pub number_width: usize,
+    /// Alignment of the line numbers: `ln` for left, `rn` for right or `rz` for right with leading zeros.
+    #[arg(long = "number-format", value_name = "FORMAT", value_enum, default_value_t = NumberFormat::Rn)]
+    pub number_format: NumberFormat,
+    /// String printed between the line numbers and the lines.
+    #[arg(long = "number-separator", value_name = "STRING", default_value = "\t")]
+    pub number_separator: String,
+    /// First line number of the output.
+    #[arg(long = "starting-line-number", value_name = "N", default_value_t = 1)]
+    pub starting_line_number: usize,
+    /// Amount added to the line number for each numbered line.
+    #[arg(long = "line-increment", value_name = "N", default_value_t = 1)]
+    pub line_increment: usize,
 }
 
 impl Cli {
@@ -81,6 +324,8 @@ impl Cli {
         let mut cli = Cli::parse();
         // Adjust combined flags
         cli.adjust_combined_flags();
+        // Apply the numbering style
+        cli.apply_number_style();
         // Override show_line_numbers
         cli.override_show_line_numbers();
 
@@ -108,6 +353,34 @@ impl Cli {
             self.show_tabs = true;
         }
     }
+    /// Sets the numbering flags selected by `--number-style`.
+    ///
+    /// Numbering all lines and non-blank lines are the same as `-n` and `-b`. Numbering the lines
+    /// matching a regex replaces both.
+    fn apply_number_style(&mut self) {
+        match self.number_style {
+            Some(NumberStyle::All) => self.show_line_numbers = true,
+            Some(NumberStyle::NonBlank) => self.show_non_blank_line_numbers = true,
+            Some(NumberStyle::Matching(_)) => {
+                self.show_line_numbers = false;
+                self.show_non_blank_line_numbers = false;
+            }
+            None => {}
+        }
+    }
+    /// Returns the byte that ends the lines, or records, of the files.
+    pub fn line_separator(&self) -> u8 {
+        if self.null_data {
+            b'\0'
+        } else {
+            self.record_separator.unwrap_or(b'\n')
+        }
+    }
+    /// Checks if line numbers depend on the content of the lines, rather than only on their count.
+    pub fn numbers_by_content(&self) -> bool {
+        self.show_non_blank_line_numbers
+            || matches!(self.number_style, Some(NumberStyle::Matching(_)))
+    }
     /// Overrides the `show_line_numbers` flag if `show_non_blank_line_numbers` is set.
     ///
     /// This ensures that non-blank line numbers are shown correctly without conflicting
@@ -118,3 +391,96 @@ impl Cli {
         }
     }
 }
+
+/// When to print decorations such as file headers and separators.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Decorations {
+    /// Print decorations only if the output is a terminal.
+    Auto,
+    /// Always print decorations.
+    Always,
+    /// Never print decorations.
+    Never,
+}
+
+/// Parses the byte of `--record-separator`, a single ASCII character or one of the escapes
+/// `\0`, `\t`, `\n` and `\r`.
+fn parse_record_separator(s: &str) -> Result<u8, String> {
+    match s {
+        "\\0" => Ok(b'\0'),
+        "\\t" => Ok(b'\t'),
+        "\\n" => Ok(b'\n'),
+        "\\r" => Ok(b'\r'),
+        s if s.len() == 1 && s.is_ascii() => Ok(s.as_bytes()[0]),
+        s => Err(format!(
+            "invalid record separator '{}', expected a single ASCII character or \\0, \\t, \\n or \\r",
+            s
+        )),
+    }
+}
+
+/// The line endings output by `--eol`.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum LineEnding {
+    /// Keep the line endings of the files.
+    Keep,
+    /// End every line with `\n`.
+    Lf,
+    /// End every line with `\r\n`.
+    Crlf,
+}
+
+/// The notations of non-printing characters selected by `--nonprinting-style`.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum NonprintingStyle {
+    /// Caret notation, such as `^[`, like GNU cat.
+    Caret,
+    /// Unicode control pictures, such as `␛`.
+    Picture,
+    /// Hexadecimal escapes, such as `\x1b`.
+    Hex,
+    /// C-style escapes, such as `\e` or `\r`.
+    Escape,
+}
+
+/// The lines numbered by `--number-style`, named like the styles of `nl`.
+#[derive(Clone, Debug)]
+pub enum NumberStyle {
+    /// Number all lines, `a`.
+    All,
+    /// Number the non-blank lines, `t`.
+    NonBlank,
+    /// Number the lines matching a regex, `pREGEX`.
+    Matching(Regex),
+}
+
+impl FromStr for NumberStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "a" => Ok(NumberStyle::All),
+            "t" => Ok(NumberStyle::NonBlank),
+            s => match s.strip_prefix('p') {
+                Some(pattern) => Regex::new(pattern)
+                    .map(NumberStyle::Matching)
+                    .map_err(|e| e.to_string()),
+                None => Err(format!(
+                    "invalid numbering style '{}', expected a, t or pREGEX",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// The alignment of the line numbers, named like the formats of `nl`.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum NumberFormat {
+    /// Left-aligned, `ln`.
+    Ln,
+    /// Right-aligned, `rn`, as `cat -n` does.
+    Rn,
+    /// Right-aligned with leading zeros, `rz`.
+    Rz,
+}
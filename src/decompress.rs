@@ -0,0 +1,105 @@
+//! # Decompression Module for RustCat
+//!
+//! This module implements `--decompress`, which decodes compressed inputs in-process so that
+//! every other option applies to their content. The compression format of each file is detected
+//! from its magic bytes rather than from its extension, and files that are not compressed are
+//! read as they are, so compressed and uncompressed files can be concatenated. Archive members are
+//! decoded the same way.
+//!
+//! Supported formats are gzip, zstd, bzip2 and xz. Files made of several concatenated streams,
+//! as produced by appending to a compressed log, are decoded entirely.
+use std::fs::File;
+use std::io::{self, Read};
+
+/// The longest magic number of the supported formats, in bytes.
+const MAGIC_LEN: usize = 6;
+
+/// A reader with the magic bytes read from it put back in front of the rest of its content.
+type Rewound<R> = io::Chain<io::Cursor<Vec<u8>>, R>;
+
+/// A compression format that can be decoded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    /// The gzip format, starting with `1f 8b`.
+    Gzip,
+    /// The zstd format, starting with `28 b5 2f fd`.
+    Zstd,
+    /// The bzip2 format, starting with `BZh`.
+    Bzip2,
+    /// The xz format, starting with `fd 37 7a 58 5a 00`.
+    Xz,
+}
+
+impl Compression {
+    /// Detects the compression format from the first bytes of a file.
+    fn from_magic(magic: &[u8]) -> Option<Self> {
+        match magic {
+            [0x1f, 0x8b, ..] => Some(Compression::Gzip),
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => Some(Compression::Zstd),
+            // The fourth byte is the block size, from 1 to 9
+            [b'B', b'Z', b'h', b'1'..=b'9', ..] => Some(Compression::Bzip2),
+            [0xfd, b'7', b'z', b'X', b'Z', 0x00, ..] => Some(Compression::Xz),
+            _ => None,
+        }
+    }
+
+    /// Wraps a reader of compressed data into a reader of the decoded data.
+    fn decoder<'a>(self, reader: impl Read + 'a) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Compression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(reader)),
+            Compression::Zstd => Box::new(zstd::stream::read::Decoder::new(reader)?),
+            Compression::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(reader)),
+            Compression::Xz => Box::new(xz2::read::XzDecoder::new_multi_decoder(reader)),
+        })
+    }
+}
+
+/// Opens a file for reading, decoding its content if it is compressed.
+///
+/// Returns `None` if the file is a regular file that is not compressed, so that the caller can
+/// read it directly, which unlike decoded data supports seeking. Other inputs, such as named
+/// pipes, cannot be opened again once their magic bytes were read, so they are always returned.
+///
+/// # Examples
+///
+/// ```
+/// if let Some(mut reader) = open_decompressed("app.log.1.gz")? {
+///     io::copy(&mut reader, &mut io::stdout())?;
+/// }
+/// ```
+pub fn open_decompressed(filename: &str) -> io::Result<Option<Box<dyn Read>>> {
+    let file = File::open(filename)?;
+    let is_regular_file = file.metadata()?.is_file();
+    let (compression, reader) = detect_compression(file)?;
+    match compression {
+        Some(compression) => compression.decoder(reader).map(Some),
+        None if is_regular_file => Ok(None),
+        None => Ok(Some(Box::new(reader))),
+    }
+}
+
+/// Wraps a reader into a reader of its decoded content if it is compressed.
+///
+/// This is used for inputs that are already read from memory, such as archive members.
+pub fn decompress<'a>(reader: impl Read + 'a) -> io::Result<Box<dyn Read + 'a>> {
+    let (compression, reader) = detect_compression(reader)?;
+    match compression {
+        Some(compression) => compression.decoder(reader),
+        None => Ok(Box::new(reader)),
+    }
+}
+
+/// Reads the magic bytes of a reader to detect its compression format.
+///
+/// Returns the format along with a reader of the whole content, magic bytes included.
+fn detect_compression<R: Read>(mut reader: R) -> io::Result<(Option<Compression>, Rewound<R>)> {
+    let mut magic = Vec::with_capacity(MAGIC_LEN);
+    reader
+        .by_ref()
+        .take(MAGIC_LEN as u64)
+        .read_to_end(&mut magic)?;
+
+    let compression = Compression::from_magic(&magic);
+    // Put the magic bytes back in front of the rest of the content
+    Ok((compression, io::Cursor::new(magic).chain(reader)))
+}
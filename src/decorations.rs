@@ -0,0 +1,151 @@
+//! # Decorations Module for RustCat
+//!
+//! This module prints the decorations that mark where each concatenated file starts and ends:
+//! a `bat`-style header box with the path, size and language of each file, and a separator line
+//! between files.
+//!
+//! Like `bat`, decorations are only printed when the output is a terminal, so that the output of
+//! RustCat can still be piped or redirected as is, unless `--decorations=always` is given.
+use crate::args::{Cli, Decorations};
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+
+/// The width of the boxes if the width of the terminal is unknown.
+const DEFAULT_WIDTH: usize = 80;
+/// The width of the left column of the header box, as wide as the line number gutter.
+const GUTTER_WIDTH: usize = 7;
+/// The units of the file sizes, each 1024 times the previous one.
+const SIZE_UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+/// Prints the headers and separators around the files.
+pub struct Decorator<'a> {
+    /// The command-line options.
+    cli: &'a Cli,
+    /// Flag to print decorations at all.
+    enabled: bool,
+    /// The width of the lines of the header box.
+    width: usize,
+}
+
+impl<'a> Decorator<'a> {
+    /// Constructs a new `Decorator`, deciding whether decorations are printed for this output.
+    pub fn new(cli: &'a Cli) -> Self {
+        let enabled = match cli.decorations {
+            Decorations::Auto => io::stdout().is_terminal(),
+            Decorations::Always => true,
+            Decorations::Never => false,
+        };
+        // Only file names are printed when listing files
+        let is_listing = cli.files_with_matches || cli.files_without_match;
+        Decorator {
+            cli,
+            enabled: enabled && !is_listing,
+            width: terminal_width().unwrap_or(DEFAULT_WIDTH),
+        }
+    }
+
+    /// Prints the decorations before the file at `index`: the separator, then the header.
+    ///
+    /// The separator is printed on a line of its own even if the previous file did not end with a
+    /// newline, which `is_line_unterminated` tells.
+    pub fn display_before_file(
+        &self,
+        filename: &str,
+        index: usize,
+        language: &str,
+        is_line_unterminated: bool,
+        handle: &mut io::StdoutLock<'_>,
+    ) -> io::Result<()> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if let (Some(separator), true) = (&self.cli.separator, index > 0) {
+            // The bottom of the header box already ended the line
+            if is_line_unterminated && !self.cli.header {
+                writeln!(handle)?;
+            }
+            writeln!(handle, "{}", separator)?;
+        }
+        if self.cli.header {
+            writeln!(handle, "{}", self.rule('┬'))?;
+            writeln!(handle, "{}│ File: {}", " ".repeat(GUTTER_WIDTH), filename)?;
+            // Archive members have no size of their own on disk
+            if let Ok(metadata) = fs::metadata(filename) {
+                let size = format_size(metadata.len());
+                writeln!(handle, "{}│ Size: {}", " ".repeat(GUTTER_WIDTH), size)?;
+            }
+            writeln!(
+                handle,
+                "{}│ Language: {}",
+                " ".repeat(GUTTER_WIDTH),
+                language
+            )?;
+            writeln!(handle, "{}", self.rule('┼'))?;
+        }
+        Ok(())
+    }
+
+    /// Prints the decorations after a file: the bottom of the header box.
+    ///
+    /// The box is closed on a line of its own even if the file does not end with a newline.
+    pub fn display_after_file(
+        &self,
+        ends_with_newline: bool,
+        handle: &mut io::StdoutLock<'_>,
+    ) -> io::Result<()> {
+        if self.enabled && self.cli.header {
+            if !ends_with_newline {
+                writeln!(handle)?;
+            }
+            writeln!(handle, "{}", self.rule('┴'))?;
+        }
+        Ok(())
+    }
+
+    /// Formats a horizontal line of the header box, crossing the gutter line with `junction`.
+    fn rule(&self, junction: char) -> String {
+        let right_width = self.width.saturating_sub(GUTTER_WIDTH + 1);
+        format!(
+            "{}{}{}",
+            "─".repeat(GUTTER_WIDTH),
+            junction,
+            "─".repeat(right_width)
+        )
+    }
+}
+
+/// Formats a size in bytes with a binary unit, such as `12 B` or `1.5 KiB`.
+fn format_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < SIZE_UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, SIZE_UNITS[0])
+    } else {
+        format!("{:.1} {}", size, SIZE_UNITS[unit])
+    }
+}
+
+/// Returns the width of the terminal that stdout is connected to, if any.
+#[cfg(unix)]
+fn terminal_width() -> Option<usize> {
+    // SAFETY: `winsize` is plain data, and is only read if `ioctl` fills it.
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    // SAFETY: `TIOCGWINSZ` writes a `winsize` to the given pointer, which is valid.
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    (result == 0 && size.ws_col > 0).then_some(size.ws_col as usize)
+}
+
+/// Returns the width of the terminal given by the `COLUMNS` environment variable, if any, where
+/// the terminal cannot be asked for its size.
+#[cfg(not(unix))]
+fn terminal_width() -> Option<usize> {
+    std::env::var("COLUMNS")
+        .ok()?
+        .parse()
+        .ok()
+        .filter(|&width| width > 0)
+}
@@ -6,9 +6,17 @@
 //!
 //! The module leverages the `LineProcessor` for processing lines of the files and
 //! utilizes Rust's standard I/O capabilities for outputting the processed text.
+use crate::archive;
 use crate::args::Cli;
+use crate::decompress;
+use crate::decorations::Decorator;
+use crate::follow::{self, FollowedFile};
+use crate::io::{error_message, read_file_list, read_line_until, PartialLines};
 use crate::process_lines::LineProcessor;
-use std::io::{self};
+use crate::walk;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
 use std::process;
 /// Displays the output for the given filenames according to the specified CLI options.
 ///
@@ -16,6 +24,20 @@ use std::process;
 /// `LineProcessor`, and then displays the processed lines. It handles I/O errors and
 /// exits the process if any occur while reading the files.
 ///
+/// When searching, it also lists the files selected by `--files-with-matches` or
+/// `--files-without-match` and prints a summary on stderr if several files were searched.
+///
+/// The files listed by `--files-from` are displayed after the given ones.
+/// With `--recursive`, directories are replaced by the regular files they contain.
+/// Headers and separators are printed around the files if requested and stdout is a terminal.
+///
+/// With `--list-archive`, the members of the archives are listed instead.
+///
+/// With `--follow`, the data appended to the files is then displayed until the process is interrupted.
+///
+/// With `--check-bidi`, the process exits with status 1 once everything is displayed if any
+/// bidirectional control character was found.
+///
 /// # Arguments
 ///
 /// * `filenames` - A slice of `String` containing the paths of the files to be processed and displayed.
@@ -28,26 +50,321 @@ pub fn display_output(filenames: &[String], cli: &Cli) {
     let stdout = io::stdout(); // Get handle to stdout
     let mut handle = stdout.lock(); // Lock the handle to stdout
     let mut line_processor = LineProcessor::new(cli); // Initialize the line processor
-                                                      // Iterate over each filename
-    for filename in filenames {
-        // Read the lines of the file
-        match super::io::read_lines(filename) {
-            Ok(lines) => {
+    let mut search_summary = SearchSummary::default(); // Initialize the search summary
+    let decorator = Decorator::new(cli); // Initialize the headers and separators
+
+    let mut followed_files = Vec::new(); // Files to follow once all were displayed
+                                         // Record interrupts from the start, so that one arriving early still stops following cleanly
+    let interrupted = cli.follow.then(follow::handle_interrupts);
+
+    let filenames = collect_operands(filenames, cli); // Add the listed files and walk directories
+
+    // List the members of archives instead of displaying them
+    if cli.list_archive {
+        for filename in &filenames {
+            display_archive_members(filename, &mut handle);
+        }
+        return;
+    }
+
+    // Iterate over each filename
+    for (index, filename) in filenames.iter().enumerate() {
+        // Whether the previous file left its last line open, before starting the next one
+        let is_line_unterminated = line_processor.is_line_unterminated;
+        line_processor.start_file(filename);
+        let decorated = decorator.display_before_file(
+            filename,
+            index,
+            line_processor.language(),
+            is_line_unterminated,
+            &mut handle,
+        );
+        if let Err(e) = decorated {
+            eprintln!("Error writing to stdout: {}", e);
+        }
+        // Read the lines of the file, displaying them as they are read if they are timestamped
+        let read_result = if let Some((archive_name, member)) = archive::split_operand(filename) {
+            // Archive members cannot seek either
+            archive::read_member(archive_name, member)
+                .and_then(|reader| {
+                    // Members are often compressed on their own, as rotated logs are
+                    if cli.decompress {
+                        decompress::decompress(reader)
+                    } else {
+                        Ok(reader)
+                    }
+                })
+                .and_then(|reader| read_unseekable(reader, cli, index + 1 == filenames.len()))
+        } else if is_streaming(cli) {
+            stream_file(filename, cli, &mut line_processor, &mut handle)
+        } else {
+            read_file(filename, cli, index + 1 == filenames.len())
+        };
+        match read_result {
+            Ok(mut partial_lines) => {
+                // An unterminated last line is completed by the data appended later
+                let mut pending = Vec::new();
+                if cli.follow
+                    && partial_lines
+                        .lines
+                        .last()
+                        .is_some_and(|line| !line.ends_with(cli.line_separator() as char))
+                {
+                    let line = partial_lines.lines.pop().unwrap_or_default();
+                    pending = partial_lines.line_bytes(&line);
+                }
+                line_processor.line_byte_offset = partial_lines.offset;
+                line_processor.has_raw_bytes = partial_lines.has_raw_bytes;
+                // Number the lines that were not read as if they had been
+                line_processor.skip_line_numbers(partial_lines.lines_before);
                 // Process and display the lines
-                line_processor.process_and_display_lines(&lines, &mut handle);
+                line_processor.process_and_display_lines(&partial_lines.lines, &mut handle);
+                line_processor.skip_line_numbers(partial_lines.lines_after);
+                if line_processor.has_mixed_line_endings() {
+                    eprintln!(
+                        "rustcat: {}: warning: mixed line endings ({} CRLF, {} LF)",
+                        filename, line_processor.crlf_line_count, line_processor.lf_line_count
+                    );
+                }
+                search_summary.add_file(line_processor.matching_line_count);
+                display_file_match(
+                    filename,
+                    line_processor.matching_line_count,
+                    cli,
+                    &mut handle,
+                );
+                let decorated = line_processor
+                    .display_end_of_file(&mut handle)
+                    .and_then(|()| {
+                        let ends_with_newline = !line_processor.is_line_unterminated;
+                        decorator.display_after_file(ends_with_newline, &mut handle)
+                    });
+                if let Err(e) = decorated {
+                    eprintln!("Error writing to stdout: {}", e);
+                }
+                // Archive members are not followed, only files are appended to
+                if cli.follow && archive::split_operand(filename).is_none() {
+                    // Continue right after the data that was read
+                    let read_len: usize = partial_lines
+                        .lines
+                        .iter()
+                        .map(|line| partial_lines.line_bytes(line).len())
+                        .sum();
+                    followed_files.push(FollowedFile::new(
+                        filename,
+                        partial_lines.offset + read_len + pending.len(),
+                        pending,
+                        line_processor.syntax_state.take(),
+                    ));
+                }
             }
             // Handle I/O errors
             Err(e) => {
-                eprintln!(
-                    "rustcat: {}: {}",
-                    filename,
-                    e.to_string()
-                        .split(" (os error")
-                        .next()
-                        .unwrap_or(&e.to_string())
-                );
+                eprintln!("rustcat: {}: {}", filename, error_message(&e));
                 process::exit(1);
             }
         }
     }
+    if let Some(interrupted) = &interrupted {
+        follow::follow_files(
+            followed_files,
+            interrupted,
+            &mut line_processor,
+            &mut handle,
+        );
+    }
+    // Summarize searches over several files
+    if cli.search.is_some() && filenames.len() > 1 {
+        eprintln!("rustcat: {}", search_summary);
+    }
+    // Fail checks for bidirectional control characters that found some
+    if cli.check_bidi && line_processor.bidi_control_count > 0 {
+        process::exit(1);
+    }
+}
+
+/// Collects the files to display: the operands, followed by the files listed by `--files-from`,
+/// with directories replaced by the files they contain when recursing.
+///
+/// Exits the process with an error message if the list of files cannot be read.
+fn collect_operands(filenames: &[String], cli: &Cli) -> Vec<String> {
+    let mut operands = filenames.to_vec();
+    if let Some(list) = &cli.files_from {
+        match read_file_list(list, cli.null) {
+            Ok(listed) => operands.extend(listed),
+            Err(e) => {
+                eprintln!("rustcat: {}: {}", list, error_message(&e));
+                process::exit(1);
+            }
+        }
+    }
+    if cli.recursive {
+        operands = walk::expand_operands(&operands, cli);
+    }
+    operands
+}
+
+/// Reads the lines of a file, or only the part of them selected by the command-line options.
+///
+/// `--head` and `--tail` read as little of the file as possible, unless squeezing blank lines or
+/// numbering non-blank or matching lines, which need every line to number the displayed ones correctly.
+fn read_file(filename: &str, cli: &Cli, is_last: bool) -> io::Result<PartialLines> {
+    let needs_all_lines = cli.squeeze_blank.is_some() || cli.numbers_by_content();
+    let separator = cli.line_separator();
+
+    // Decoded data cannot seek, so it is read like a named pipe
+    if cli.decompress {
+        if let Some(reader) = decompress::open_decompressed(filename)? {
+            return read_unseekable(reader, cli, is_last);
+        }
+    }
+    if let Some(range) = &cli.bytes {
+        super::io::read_byte_range(filename, range, separator)
+    } else if let (Some(count), false) = (cli.head, needs_all_lines) {
+        // The lines after the head only matter to the numbering of the following files
+        let count_rest = cli.show_line_numbers && !is_last;
+        super::io::read_head_lines(filename, count, count_rest, separator)
+    } else if let (Some(count), false) = (cli.tail, needs_all_lines) {
+        super::io::read_tail_lines(filename, count, cli.show_line_numbers, separator)
+    } else {
+        super::io::read_lines(filename, separator).map(PartialLines::from)
+    }
+}
+
+/// Reads the lines of data that cannot seek, such as decoded data or archive members, or only
+/// the part of them selected by the command-line options.
+fn read_unseekable(reader: Box<dyn Read>, cli: &Cli, is_last: bool) -> io::Result<PartialLines> {
+    let needs_all_lines = cli.squeeze_blank.is_some() || cli.numbers_by_content();
+    let separator = cli.line_separator();
+
+    if let Some(range) = &cli.bytes {
+        super::io::read_byte_range_unseekable(reader, range, separator)
+    } else if let (Some(count), false) = (cli.head, needs_all_lines) {
+        let count_rest = cli.show_line_numbers && !is_last;
+        super::io::read_head_lines_from(reader, count, count_rest, separator)
+    } else if let (Some(count), false) = (cli.tail, needs_all_lines) {
+        super::io::read_tail_lines_unseekable(reader, count, separator)
+    } else {
+        super::io::read_remaining_lines(&mut BufReader::new(reader), separator)
+            .map(PartialLines::from)
+    }
+}
+
+/// Checks if lines are displayed as soon as they are read, so that `--timestamp` shows when each
+/// one arrived. Selecting lines needs to know the length of the file first, so it reads it whole.
+fn is_streaming(cli: &Cli) -> bool {
+    cli.timestamp.is_some()
+        && cli.lines.is_empty()
+        && cli.bytes.is_none()
+        && cli.head.is_none()
+        && cli.tail.is_none()
+}
+
+/// Reads and displays the lines of a file one at a time, flushing each one as soon as it is read.
+///
+/// An unterminated last line is returned instead of displayed, so that it is handled like the
+/// lines returned by `read_file`, and can be completed by `--follow`.
+fn stream_file(
+    filename: &str,
+    cli: &Cli,
+    line_processor: &mut LineProcessor,
+    handle: &mut io::StdoutLock<'_>,
+) -> io::Result<PartialLines> {
+    let decoded = if cli.decompress {
+        decompress::open_decompressed(filename)?
+    } else {
+        None
+    };
+    let mut reader: Box<dyn BufRead> = match decoded {
+        Some(reader) => Box::new(BufReader::new(reader)),
+        None => Box::new(BufReader::new(File::open(filename)?)),
+    };
+    let separator = cli.line_separator();
+    let mut line = String::new();
+    let mut offset = 0;
+
+    while read_line_until(&mut reader, &mut line, separator)? > 0 {
+        if !line.ends_with(separator as char) {
+            break;
+        }
+        offset += line.len();
+        line_processor.process_and_display_appended_lines(&[line.clone()], handle)?;
+        handle.flush()?;
+        line.clear();
+    }
+    // Only the unterminated last line is left, if any
+    let mut partial_lines = PartialLines::from(Vec::new());
+    if !line.is_empty() {
+        partial_lines.lines.push(line);
+    }
+    partial_lines.offset = offset;
+    Ok(partial_lines)
+}
+
+/// Lists the members of an archive, one per line, exiting the process if it cannot be read.
+fn display_archive_members(filename: &str, handle: &mut io::StdoutLock<'_>) {
+    let members = match archive::list_members(filename) {
+        Ok(members) => members,
+        Err(e) => {
+            eprintln!("rustcat: {}: {}", filename, error_message(&e));
+            process::exit(1);
+        }
+    };
+    for member in members {
+        if let Err(e) = writeln!(handle, "{}", member) {
+            eprintln!("Error writing to stdout: {}", e);
+            return;
+        }
+    }
+}
+
+/// Lists the filename if the `--files-with-matches` or `--files-without-match` option selects it.
+fn display_file_match(
+    filename: &str,
+    matching_line_count: usize,
+    cli: &Cli,
+    handle: &mut io::StdoutLock<'_>,
+) {
+    let is_listed = (cli.files_with_matches && matching_line_count > 0)
+        || (cli.files_without_match && matching_line_count == 0);
+    if is_listed {
+        if let Err(e) = writeln!(handle, "{}", filename) {
+            eprintln!("Error writing to stdout: {}", e);
+        }
+    }
+}
+
+/// Totals of a search over several files, reported on stderr once all files are processed.
+#[derive(Default)]
+struct SearchSummary {
+    /// The number of matching lines over all files.
+    matching_lines: usize,
+    /// The number of files with at least one matching line.
+    matching_files: usize,
+    /// The number of files searched.
+    files: usize,
+}
+
+impl SearchSummary {
+    /// Adds the result of searching one file to the summary.
+    fn add_file(&mut self, matching_line_count: usize) {
+        self.matching_lines += matching_line_count;
+        self.files += 1;
+        if matching_line_count > 0 {
+            self.matching_files += 1;
+        }
+    }
+}
+
+impl fmt::Display for SearchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} matching line{} in {} of {} files",
+            self.matching_lines,
+            if self.matching_lines == 1 { "" } else { "s" },
+            self.matching_files,
+            self.files
+        )
+    }
 }
@@ -0,0 +1,316 @@
+//! # Follow Module for RustCat
+//!
+//! This module implements `--follow`, which keeps displaying the data appended to files after
+//! their end has been reached, the way `tail -F` does. Files are followed by name: a file that
+//! shrinks is considered truncated and read again from its start, and a file that is replaced
+//! by another one, as happens with log rotation, is reopened once the rest of the old file has
+//! been displayed.
+//!
+//! On Linux, changes are waited for with `inotify` on the directories of the followed files.
+//! Elsewhere, or if `inotify` is not available, the files are polled at a fixed interval.
+//! Replaced files are recognised by their inode on Unix, and by their creation time elsewhere.
+//! Following stops cleanly when the process receives `SIGINT`, even before the files were
+//! first displayed.
+use crate::io::error_message;
+use crate::process_lines::LineProcessor;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use syntect::highlighting::HighlightState;
+use syntect::parsing::ParseState;
+
+/// How long to wait for changes before checking the files again.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A file whose appended data is displayed as it arrives.
+pub struct FollowedFile {
+    /// The name the file is followed by.
+    name: String,
+    /// The currently open file, if it exists.
+    file: Option<File>,
+    /// The inode of the currently open file, or its creation time where there are no inodes, used
+    /// to detect that the file was replaced.
+    inode: u64,
+    /// The number of bytes of the currently open file that were read.
+    position: u64,
+    /// The byte offset of the first line that has not been displayed yet.
+    line_byte_offset: usize,
+    /// The bytes of a last line that has not been terminated yet.
+    pending: Vec<u8>,
+    /// The syntax highlighting state of the file, kept while other files are displayed.
+    syntax_state: Option<(ParseState, HighlightState)>,
+}
+
+impl FollowedFile {
+    /// Starts following a file from the given byte `position`, right after the lines that were displayed.
+    ///
+    /// `pending` is the start of a last line that was not terminated yet, which is displayed once complete.
+    pub fn new(
+        name: &str,
+        position: usize,
+        pending: Vec<u8>,
+        syntax_state: Option<(ParseState, HighlightState)>,
+    ) -> Self {
+        let mut followed_file = FollowedFile {
+            name: name.to_string(),
+            file: None,
+            inode: 0,
+            position: position as u64,
+            line_byte_offset: position - pending.len(),
+            pending,
+            syntax_state,
+        };
+        // The file may be missing, it is then opened once it is created
+        if let Ok(file) = File::open(name) {
+            followed_file.inode = file.metadata().map_or(0, |metadata| file_id(&metadata));
+            followed_file.file = Some(file);
+        }
+        followed_file
+    }
+
+    /// Forgets everything read from the current file, to read it again from its start.
+    fn restart(&mut self) {
+        self.position = 0;
+        self.line_byte_offset = 0;
+        self.pending.clear();
+        self.syntax_state = None;
+    }
+
+    /// Reads the data appended to the current file since it was last read.
+    fn read_appended_bytes(&mut self) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        let len = match &self.file {
+            Some(file) => file.metadata()?.len(),
+            None => return Ok(bytes),
+        };
+        // A file that shrank was truncated, its new content starts at the beginning
+        if len < self.position {
+            eprintln!("rustcat: {}: file truncated", self.name);
+            self.restart();
+        }
+        if let Some(file) = &mut self.file {
+            file.seek(SeekFrom::Start(self.position))?;
+            self.position += file.read_to_end(&mut bytes)? as u64;
+        }
+        Ok(bytes)
+    }
+
+    /// Checks if the name of the file now refers to another file, as happens with log rotation.
+    fn is_replaced(&self) -> bool {
+        match fs::metadata(&self.name) {
+            Ok(metadata) => self.file.is_none() || file_id(&metadata) != self.inode,
+            // Keep reading the current file until a new one is created
+            Err(_) => false,
+        }
+    }
+
+    /// Opens the file the name now refers to, to read it from its start.
+    fn reopen(&mut self) -> io::Result<()> {
+        let file = File::open(&self.name)?;
+        if self.file.is_some() {
+            eprintln!(
+                "rustcat: {}: file replaced, following the new file",
+                self.name
+            );
+        } else {
+            eprintln!("rustcat: {}: file appeared, following it", self.name);
+        }
+        self.inode = file_id(&file.metadata()?);
+        self.file = Some(file);
+        self.restart();
+        Ok(())
+    }
+
+    /// Reads the complete lines appended to the file since it was last read.
+    ///
+    /// A last line without a `separator` is kept until the rest of it is appended. Once a replaced
+    /// file has been read completely, its last line is terminated and the new file is opened.
+    fn read_appended_lines(&mut self, separator: u8) -> io::Result<Vec<String>> {
+        let mut bytes = self.read_appended_bytes()?;
+        if bytes.is_empty() && self.is_replaced() {
+            // Display the unterminated last line of the replaced file before switching
+            if !self.pending.is_empty() {
+                let mut last_line = mem::take(&mut self.pending);
+                last_line.push(separator);
+                return Ok(vec![String::from_utf8_lossy(&last_line).into_owned()]);
+            }
+            self.reopen()?;
+            bytes = self.read_appended_bytes()?;
+        }
+
+        let mut pending = mem::take(&mut self.pending);
+        pending.extend(bytes);
+        let mut lines = Vec::new();
+        for line in pending.split_inclusive(|&byte| byte == separator) {
+            if line.ends_with(&[separator]) {
+                lines.push(String::from_utf8_lossy(line).into_owned());
+            } else {
+                self.pending = line.to_vec();
+            }
+        }
+        Ok(lines)
+    }
+}
+
+/// Returns the inode of a file, which tells whether a name still refers to the same file.
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    metadata.ino()
+}
+
+/// Returns the creation time of a file in nanoseconds, which tells whether a name still refers to
+/// the same file where there are no inodes.
+#[cfg(not(unix))]
+fn file_id(metadata: &fs::Metadata) -> u64 {
+    metadata
+        .created()
+        .ok()
+        .and_then(|created| created.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |created| created.as_nanos() as u64)
+}
+
+/// Waits for the followed files to change.
+enum ChangeWaiter {
+    /// Waits for `inotify` events on the directories of the followed files.
+    #[cfg(target_os = "linux")]
+    Inotify(inotify::Inotify),
+    /// Waits for a fixed interval.
+    Polling,
+}
+
+impl ChangeWaiter {
+    /// Constructs a new `ChangeWaiter` for the given files, falling back to polling if needed.
+    #[cfg(target_os = "linux")]
+    fn new(files: &[FollowedFile]) -> Self {
+        use inotify::{Inotify, WatchMask};
+        use std::path::Path;
+
+        let inotify = match Inotify::init() {
+            Ok(inotify) => inotify,
+            Err(_) => return ChangeWaiter::Polling,
+        };
+        // Watching the directories also reports files being created, moved or deleted
+        let mask = WatchMask::MODIFY
+            | WatchMask::ATTRIB
+            | WatchMask::CREATE
+            | WatchMask::DELETE
+            | WatchMask::MOVED_FROM
+            | WatchMask::MOVED_TO;
+        for followed_file in files {
+            let directory = match Path::new(&followed_file.name).parent() {
+                Some(directory) if !directory.as_os_str().is_empty() => directory,
+                _ => Path::new("."),
+            };
+            if inotify.watches().add(directory, mask).is_err() {
+                return ChangeWaiter::Polling;
+            }
+        }
+        ChangeWaiter::Inotify(inotify)
+    }
+
+    /// Constructs a new `ChangeWaiter` that polls the files.
+    #[cfg(not(target_os = "linux"))]
+    fn new(_files: &[FollowedFile]) -> Self {
+        ChangeWaiter::Polling
+    }
+
+    /// Waits until a file may have changed, at most for `POLL_INTERVAL`.
+    fn wait(&mut self) {
+        match self {
+            #[cfg(target_os = "linux")]
+            ChangeWaiter::Inotify(inotify) => {
+                use std::os::fd::AsRawFd;
+
+                let mut poll_fd = libc::pollfd {
+                    fd: inotify.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                };
+                // SAFETY: `poll_fd` is a valid `pollfd` for the duration of the call.
+                // The call is interrupted early by signals such as `SIGINT`.
+                unsafe { libc::poll(&mut poll_fd, 1, POLL_INTERVAL.as_millis() as libc::c_int) };
+                // Drain the events, the files are checked regardless of what changed
+                let mut buffer = [0; 4096];
+                while let Ok(events) = inotify.read_events(&mut buffer) {
+                    if events.count() == 0 {
+                        break;
+                    }
+                }
+            }
+            ChangeWaiter::Polling => thread::sleep(POLL_INTERVAL),
+        }
+    }
+}
+
+/// Starts recording `SIGINT` in the returned flag instead of letting it end the process.
+///
+/// This is done before the files are first displayed, so that an interrupt arriving at any time
+/// stops following cleanly.
+pub fn handle_interrupts() -> Arc<AtomicBool> {
+    let interrupted = Arc::new(AtomicBool::new(false));
+    if let Err(e) =
+        signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&interrupted))
+    {
+        eprintln!("rustcat: cannot handle interrupts: {}", e);
+    }
+    interrupted
+}
+
+/// Displays the data appended to the given files until `interrupted` is set by `SIGINT`.
+///
+/// Line numbers and squeezing continue from where the initial display of the files stopped.
+pub fn follow_files(
+    mut files: Vec<FollowedFile>,
+    interrupted: &AtomicBool,
+    line_processor: &mut LineProcessor,
+    handle: &mut io::StdoutLock<'_>,
+) {
+    let mut change_waiter = ChangeWaiter::new(&files);
+
+    while !interrupted.load(Ordering::Relaxed) {
+        for followed_file in &mut files {
+            let lines = match followed_file.read_appended_lines(line_processor.cli.line_separator())
+            {
+                Ok(lines) => lines,
+                Err(e) => {
+                    eprintln!("rustcat: {}: {}", followed_file.name, error_message(&e));
+                    continue;
+                }
+            };
+            if lines.is_empty() {
+                continue;
+            }
+            // Continue from the state of this file
+            line_processor.file_name = followed_file.name.clone();
+            line_processor.line_byte_offset = followed_file.line_byte_offset;
+            // Appended lines are read as valid UTF-8, even after a window with raw bytes
+            line_processor.has_raw_bytes = false;
+            mem::swap(
+                &mut line_processor.syntax_state,
+                &mut followed_file.syntax_state,
+            );
+            let result = line_processor.process_and_display_appended_lines(&lines, handle);
+            mem::swap(
+                &mut line_processor.syntax_state,
+                &mut followed_file.syntax_state,
+            );
+            followed_file.line_byte_offset = line_processor.line_byte_offset;
+            // Stop following once the output is closed
+            if let Err(e) = result {
+                eprintln!("Error writing to stdout: {}", e);
+                return;
+            }
+        }
+        if let Err(e) = handle.flush() {
+            eprintln!("Error writing to stdout: {}", e);
+            break;
+        }
+        change_waiter.wait();
+    }
+}
@@ -4,17 +4,83 @@
 //! It primarily deals with reading files and processing their contents into a usable format.
 //! The functionality here is essential for the core operation of RustCat, facilitating
 //! the reading of file lines to be processed and displayed.
+use crate::selection::{RangeBound, SelectionRange};
+use std::collections::VecDeque;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+
+/// The size of the chunks read when scanning a file for line endings.
+const SCAN_CHUNK_SIZE: usize = 8192;
+/// The private use code point standing for the byte 0, so that the bytes of a `--bytes` window
+/// that are not valid UTF-8, from `0x80` to `0xff`, are kept as `U+10FF80` to `U+10FFFF`.
+const RAW_BYTE_BASE: u32 = 0x10ff00;
+
+/// Some of the lines of a file, together with where they are located in it.
+///
+/// Lines that are not read are only counted, and only if they are needed for numbering.
+pub struct PartialLines {
+    /// The lines that were read.
+    pub lines: Vec<String>,
+    /// The byte offset of the first line within the file.
+    pub offset: usize,
+    /// The number of lines before the first line that were counted.
+    pub lines_before: usize,
+    /// The number of lines after the last line that were counted.
+    pub lines_after: usize,
+    /// Whether the lines keep bytes that are not valid UTF-8 as raw bytes, as `--bytes` windows do.
+    pub has_raw_bytes: bool,
+}
+
+impl From<Vec<String>> for PartialLines {
+    fn from(lines: Vec<String>) -> Self {
+        PartialLines {
+            lines,
+            offset: 0,
+            lines_before: 0,
+            lines_after: 0,
+            has_raw_bytes: false,
+        }
+    }
+}
+
+impl PartialLines {
+    /// Returns the bytes a line was read from.
+    pub fn line_bytes(&self, line: &str) -> Vec<u8> {
+        if self.has_raw_bytes {
+            encode_raw_bytes(line)
+        } else {
+            line.as_bytes().to_vec()
+        }
+    }
+}
+/// Formats an I/O error for display, without the OS error code.
+///
+/// # Examples
+///
+/// ```
+/// let error = std::fs::File::open("missing.txt").unwrap_err();
+/// assert_eq!(error_message(&error), "No such file or directory");
+/// ```
+pub fn error_message(error: &io::Error) -> String {
+    let message = error.to_string();
+    message
+        .split(" (os error")
+        .next()
+        .unwrap_or(&message)
+        .to_string()
+}
+
 /// Reads all lines from a specified file and returns them.
 ///
-/// This function opens the file specified by `filename` and reads it line by line,
+/// This function opens the file specified by `filename` and reads it line by line, each line
+/// ending with `separator`,
 /// accumulating the lines into a `Vec<String>`. It uses buffered reading to efficiently
 /// handle large files. In case of any I/O errors, the function returns an `io::Result`.
 ///
 /// # Arguments
 ///
 /// * `filename` - A string slice that holds the path to the file to be read.
+/// * `separator` - The byte ending each line, `\n` unless reading records.
 ///
 /// # Returns
 ///
@@ -24,18 +90,413 @@ use std::io::{self, BufRead, BufReader};
 /// # Examples
 ///
 /// ```
-/// let lines = read_lines("example.txt").expect("Failed to read the file");
+/// let lines = read_lines("example.txt", b'\n').expect("Failed to read the file");
 /// ```
-pub fn read_lines(filename: &str) -> io::Result<Vec<String>> {
+pub fn read_lines(filename: &str, separator: u8) -> io::Result<Vec<String>> {
     let file = File::open(filename)?; // Open the file
     let mut reader = BufReader::new(file); // Create a buffered reader
+    read_remaining_lines(&mut reader, separator)
+}
+
+/// Reads all remaining lines from a buffered reader.
+pub fn read_remaining_lines(reader: &mut impl BufRead, separator: u8) -> io::Result<Vec<String>> {
     let mut lines = Vec::new(); // Create a vector to hold the lines
     let mut line = String::new(); // Create a string to hold each line
 
     // Read each line and push it to the vector
-    while reader.read_line(&mut line)? > 0 {
+    while read_line_until(reader, &mut line, separator)? > 0 {
         lines.push(line.clone());
         line.clear();
     }
     Ok(lines)
 }
+
+/// Reads a line ending with `separator` and appends it to `line`, like `BufRead::read_line`
+/// does for lines ending with `\n`.
+pub fn read_line_until(
+    reader: &mut impl BufRead,
+    line: &mut String,
+    separator: u8,
+) -> io::Result<usize> {
+    let mut bytes = Vec::new();
+    let length = reader.read_until(separator, &mut bytes)?;
+    match String::from_utf8(bytes) {
+        Ok(text) => line.push_str(&text),
+        Err(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            ))
+        }
+    }
+    Ok(length)
+}
+
+/// Reads a window of bytes from a specified file and returns its offset and lines.
+///
+/// Regular files are seeked straight to the start of the window. Other inputs, such as named
+/// pipes, cannot seek, so the bytes before the window are read and skipped instead; they are
+/// read entirely if the range counts back from the end. The window may start or end in the
+/// middle of a character, so bytes that are not valid UTF-8 are kept as raw bytes, which `-v`
+/// shows in `M-` notation and which are otherwise written back unchanged.
+///
+/// # Arguments
+///
+/// * `filename` - A string slice that holds the path to the file to be read.
+/// * `range` - The 1-based, inclusive range of bytes to read.
+/// * `separator` - The byte ending each line.
+///
+/// # Returns
+///
+/// Returns an `io::Result<PartialLines>` with the lines of the window and its byte offset
+/// in the file in case of success, or an I/O error in case of failure.
+///
+/// # Examples
+///
+/// ```
+/// let range = "1:512".parse().unwrap();
+/// let window = read_byte_range("example.bin", &range, b'\n').expect("Failed to read the file");
+/// ```
+pub fn read_byte_range(
+    filename: &str,
+    range: &SelectionRange,
+    separator: u8,
+) -> io::Result<PartialLines> {
+    let mut file = File::open(filename)?; // Open the file
+    let metadata = file.metadata()?;
+    let mut bytes = Vec::new(); // Create a vector to hold the bytes of the window
+
+    if !metadata.is_file() {
+        return read_byte_range_unseekable(file, range, separator);
+    }
+    // Seek to the start of the window
+    let window = range.resolve(metadata.len() as usize);
+    file.seek(SeekFrom::Start(window.start as u64))?;
+    file.take(window.len() as u64).read_to_end(&mut bytes)?;
+    Ok(split_window(bytes, window.start, separator))
+}
+
+/// Reads a window of bytes from an input that cannot seek, such as a named pipe or decoded data.
+///
+/// The bytes before the window are read and skipped, and the input is read entirely if the
+/// range counts back from the end.
+pub fn read_byte_range_unseekable(
+    mut reader: impl Read,
+    range: &SelectionRange,
+    separator: u8,
+) -> io::Result<PartialLines> {
+    let mut bytes = Vec::new(); // Create a vector to hold the bytes of the window
+    let counts_from_end = [range.start, range.end]
+        .iter()
+        .any(|bound| matches!(bound, Some(RangeBound::FromEnd(_))));
+
+    let window = if counts_from_end {
+        // The length is only known once everything has been read
+        reader.read_to_end(&mut bytes)?;
+        let window = range.resolve(bytes.len());
+        bytes = bytes[window.clone()].to_vec();
+        window
+    } else {
+        // Skip the bytes before the window
+        let window = range.resolve(usize::MAX);
+        io::copy(
+            &mut reader.by_ref().take(window.start as u64),
+            &mut io::sink(),
+        )?;
+        reader.take(window.len() as u64).read_to_end(&mut bytes)?;
+        window
+    };
+    Ok(split_window(bytes, window.start, separator))
+}
+
+/// Splits a window of bytes starting at byte `offset` into lines ending with `separator`.
+///
+/// A window may cut a character in half or cover binary data, so if it is not valid UTF-8, its
+/// lines keep the bytes that are not as raw bytes, to be shown by `-v` or written back unchanged.
+fn split_window(bytes: Vec<u8>, offset: usize, separator: u8) -> PartialLines {
+    let has_raw_bytes = std::str::from_utf8(&bytes).is_err();
+    let lines = bytes
+        .split_inclusive(|&byte| byte == separator)
+        .map(|line| {
+            if has_raw_bytes {
+                decode_raw_bytes(line)
+            } else {
+                // Valid lines are decoded without loss
+                String::from_utf8_lossy(line).into_owned()
+            }
+        })
+        .collect();
+    PartialLines {
+        lines,
+        offset,
+        lines_before: 0,
+        lines_after: 0,
+        has_raw_bytes,
+    }
+}
+
+/// Decodes bytes as UTF-8, keeping each byte that is not valid UTF-8 as a raw byte.
+///
+/// The characters that raw bytes are kept as are themselves kept byte by byte, so that every one
+/// of them in the decoded string is a raw byte.
+fn decode_raw_bytes(bytes: &[u8]) -> String {
+    let mut decoded = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            if raw_byte(c).is_some() {
+                decoded.extend(c.encode_utf8(&mut [0; 4]).bytes().filter_map(raw_byte_char));
+            } else {
+                decoded.push(c);
+            }
+        }
+        decoded.extend(chunk.invalid().iter().copied().filter_map(raw_byte_char));
+    }
+    decoded
+}
+
+/// Returns the character a byte that is not valid UTF-8 is kept as.
+fn raw_byte_char(byte: u8) -> Option<char> {
+    char::from_u32(RAW_BYTE_BASE + u32::from(byte))
+}
+
+/// Returns the byte a character stands for in lines that keep raw bytes.
+pub fn raw_byte(c: char) -> Option<u8> {
+    (c as u32)
+        .checked_sub(RAW_BYTE_BASE)
+        .and_then(|code| u8::try_from(code).ok())
+        // Bytes below 0x80 are always valid UTF-8
+        .filter(|&byte| byte >= 0x80)
+}
+
+/// Encodes a line that keeps raw bytes as UTF-8, writing the raw bytes back as they were read.
+pub fn encode_raw_bytes(s: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(s.len());
+    for c in s.chars() {
+        match raw_byte(c) {
+            Some(byte) => bytes.push(byte),
+            None => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    bytes
+}
+
+/// Reads the first `count` lines from a specified file.
+///
+/// Reading stops after the last requested line. If `count_rest` is set, the remaining lines
+/// are counted without being decoded so that the lines of the following files can be numbered.
+///
+/// # Arguments
+///
+/// * `filename` - A string slice that holds the path to the file to be read.
+/// * `count` - The number of lines to read.
+/// * `count_rest` - Whether to count the lines after the ones that are read.
+/// * `separator` - The byte ending each line.
+///
+/// # Returns
+///
+/// Returns an `io::Result<PartialLines>` with the first lines of the file in case of success,
+/// or an I/O error in case of failure.
+pub fn read_head_lines(
+    filename: &str,
+    count: usize,
+    count_rest: bool,
+    separator: u8,
+) -> io::Result<PartialLines> {
+    let file = File::open(filename)?; // Open the file
+    read_head_lines_from(file, count, count_rest, separator)
+}
+
+/// Reads the first `count` lines from a reader, such as one of decoded data.
+pub fn read_head_lines_from(
+    reader: impl Read,
+    count: usize,
+    count_rest: bool,
+    separator: u8,
+) -> io::Result<PartialLines> {
+    let mut reader = BufReader::new(reader); // Create a buffered reader
+    let mut lines = Vec::new(); // Create a vector to hold the lines
+    let mut line = String::new(); // Create a string to hold each line
+
+    // Read each line until enough lines have been read
+    while lines.len() < count && read_line_until(&mut reader, &mut line, separator)? > 0 {
+        lines.push(line.clone());
+        line.clear();
+    }
+    let mut lines_after = 0;
+    if count_rest {
+        lines_after = count_lines(&mut reader, separator)?;
+    }
+    Ok(PartialLines {
+        lines,
+        offset: 0,
+        lines_before: 0,
+        lines_after,
+        has_raw_bytes: false,
+    })
+}
+
+/// Reads the last `count` lines from a specified file.
+///
+/// Regular files are scanned backwards from their end, so only the requested lines are read.
+/// If `count_skipped` is set, the lines before them are counted without being decoded so
+/// that the lines can be numbered with their original line numbers. Other inputs, such as
+/// named pipes, cannot seek, so they are read through while keeping the last `count` lines.
+///
+/// # Arguments
+///
+/// * `filename` - A string slice that holds the path to the file to be read.
+/// * `count` - The number of lines to read.
+/// * `count_skipped` - Whether to count the lines before the ones that are read.
+/// * `separator` - The byte ending each line.
+///
+/// # Returns
+///
+/// Returns an `io::Result<PartialLines>` with the last lines of the file in case of success,
+/// or an I/O error in case of failure.
+pub fn read_tail_lines(
+    filename: &str,
+    count: usize,
+    count_skipped: bool,
+    separator: u8,
+) -> io::Result<PartialLines> {
+    let mut file = File::open(filename)?; // Open the file
+    let metadata = file.metadata()?;
+
+    if !metadata.is_file() {
+        return read_tail_lines_unseekable(file, count, separator);
+    }
+    let offset = find_tail_offset(&mut file, metadata.len() as usize, count, separator)?;
+    let mut lines_before = 0;
+    if count_skipped {
+        file.seek(SeekFrom::Start(0))?;
+        let mut skipped = BufReader::new(file.by_ref().take(offset as u64));
+        lines_before = count_lines(&mut skipped, separator)?;
+    }
+    // Read the lines after the offset
+    file.seek(SeekFrom::Start(offset as u64))?;
+    let lines = read_remaining_lines(&mut BufReader::new(file), separator)?;
+    Ok(PartialLines {
+        lines,
+        offset,
+        lines_before,
+        lines_after: 0,
+        has_raw_bytes: false,
+    })
+}
+
+/// Reads the last `count` lines from an input that cannot seek, keeping them in a ring buffer.
+pub fn read_tail_lines_unseekable(
+    reader: impl Read,
+    count: usize,
+    separator: u8,
+) -> io::Result<PartialLines> {
+    let mut reader = BufReader::new(reader); // Create a buffered reader
+    let mut lines: VecDeque<String> = VecDeque::with_capacity(count); // Create a ring buffer to hold the lines
+    let mut line = String::new(); // Create a string to hold each line
+    let mut offset = 0;
+    let mut lines_before = 0;
+
+    // Read each line, dropping the oldest one once the buffer is full
+    while read_line_until(&mut reader, &mut line, separator)? > 0 {
+        if lines.len() == count {
+            match lines.pop_front() {
+                Some(dropped) => offset += dropped.len(),
+                // Nothing is kept when no lines are requested
+                None => offset += line.len(),
+            }
+            lines_before += 1;
+        }
+        if count > 0 {
+            lines.push_back(line.clone());
+        }
+        line.clear();
+    }
+    Ok(PartialLines {
+        lines: lines.into(),
+        offset,
+        lines_before,
+        lines_after: 0,
+        has_raw_bytes: false,
+    })
+}
+
+/// Finds the byte offset of the start of the last `count` lines of a file of `len` bytes.
+fn find_tail_offset(file: &mut File, len: usize, count: usize, separator: u8) -> io::Result<usize> {
+    if count == 0 || len == 0 {
+        return Ok(len);
+    }
+    let mut buffer = vec![0; SCAN_CHUNK_SIZE];
+    let mut end = len;
+    let mut newlines = 0;
+    let mut is_last_byte = true;
+
+    // Scan the file backwards chunk by chunk
+    while end > 0 {
+        let start = end.saturating_sub(SCAN_CHUNK_SIZE);
+        let chunk = &mut buffer[..end - start];
+        file.seek(SeekFrom::Start(start as u64))?;
+        file.read_exact(chunk)?;
+
+        for (index, &byte) in chunk.iter().enumerate().rev() {
+            // The newline ending the last line does not start another line
+            if byte == separator && !is_last_byte {
+                newlines += 1;
+                if newlines == count {
+                    return Ok(start + index + 1);
+                }
+            }
+            is_last_byte = false;
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+/// Counts the remaining lines of a reader, ending with `separator`, without decoding them.
+///
+/// A last line without a trailing separator is counted as well.
+fn count_lines(reader: &mut impl BufRead, separator: u8) -> io::Result<usize> {
+    let mut lines = 0;
+    let mut ends_with_newline = true;
+
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            break;
+        }
+        lines += buffer.iter().filter(|&&byte| byte == separator).count();
+        ends_with_newline = buffer.ends_with(&[separator]);
+        let length = buffer.len();
+        reader.consume(length);
+    }
+    if !ends_with_newline {
+        lines += 1;
+    }
+    Ok(lines)
+}
+
+/// Reads a list of file names from a specified file, or from standard input if it is `-`.
+///
+/// Names are separated by newlines, or by NUL bytes if `null_separated` is set, which allows
+/// names containing newlines. Empty names are skipped.
+///
+/// # Examples
+///
+/// ```
+/// // With `find . -name '*.rs' -print0 > files.txt`
+/// let filenames = read_file_list("files.txt", true).expect("Failed to read the list");
+/// ```
+pub fn read_file_list(filename: &str, null_separated: bool) -> io::Result<Vec<String>> {
+    let mut bytes = Vec::new();
+    if filename == "-" {
+        io::stdin().lock().read_to_end(&mut bytes)?;
+    } else {
+        File::open(filename)?.read_to_end(&mut bytes)?;
+    }
+    let separator = if null_separated { b'\0' } else { b'\n' };
+    Ok(bytes
+        .split(|&byte| byte == separator)
+        .filter(|name| !name.is_empty())
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect())
+}
@@ -2,16 +2,30 @@
 //!
 //! The main module of the RustCat application, an enhanced reimplementation of the Unix `cat` command.
 //! This module acts as the entry point for the application, orchestrating the overall functionality.
-//! It integrates various sub-modules like argument parsing (`args`), output display (`display`),
-//! file input/output operations (`io`), and line processing (`process_lines`).
+//! It integrates various sub-modules like argument parsing (`args`), reading archive members
+//! (`archive`), decoding compressed files (`decompress`), file headers and separators
+//! (`decorations`), output display (`display`), following appended data (`follow`), file
+//! input/output operations (`io`), line processing (`process_lines`), search matching (`search`),
+//! range selection (`selection`), tab expansion (`tabs`), line timestamps (`timestamp`),
+//! invisible Unicode characters (`unicode`), and directory walking (`walk`).
 //!
 //! The main functionality includes reading files, concatenating their contents, providing line numbering,
 //! highlighting search terms, and syntax highlighting for various programming languages.
 use args::Cli;
+mod archive;
 mod args;
+mod decompress;
+mod decorations;
 mod display;
+mod follow;
 mod io;
 mod process_lines;
+mod search;
+mod selection;
+mod tabs;
+mod timestamp;
+mod unicode;
+mod walk;
 /// The entry point of the RustCat application.
 ///
 /// Initializes the application by parsing command-line arguments using the `Cli` struct from the `args` module.
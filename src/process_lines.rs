@@ -7,11 +7,51 @@
 //! The module leverages the `syntect` library for syntax highlighting and provides custom logic for other text
 //! processing functionalities.
 
-use crate::args::Cli;
+use crate::archive;
+use crate::args::{Cli, LineEnding, NonprintingStyle, NumberFormat, NumberStyle};
+use crate::io::{encode_raw_bytes, raw_byte};
+use crate::search::SearchPattern;
+use crate::selection;
+use crate::tabs::{TabStops, DEFAULT_TAB_WIDTH};
+use crate::timestamp::Timestamper;
+use crate::unicode;
 use std::io::{self, Write};
-use syntect::easy::HighlightLines;
-use syntect::highlighting::{Theme, ThemeSet};
-use syntect::parsing::SyntaxSet;
+use std::ops::Range;
+use std::path::Path;
+use syntect::highlighting::{HighlightIterator, HighlightState, Highlighter, Style, ThemeSet};
+use syntect::parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet};
+
+/// The escape sequence used to highlight search matches.
+const SEARCH_HIGHLIGHT: &str = "\x1b[33m";
+/// The escape sequence used to colour file names.
+const FILE_NAME_HIGHLIGHT: &str = "\x1b[35m";
+/// The escape sequence that resets all colours and styles.
+const RESET: &str = "\x1b[0m";
+/// The escape sequence used to highlight trailing whitespace.
+const TRAILING_WHITESPACE_HIGHLIGHT: &str = "\x1b[41m";
+/// The escape sequences that start and end the highlighting of invisible characters, in reverse
+/// video so that the colours around them are kept.
+const UNICODE_HIGHLIGHT: (&str, &str) = ("\x1b[7m", "\x1b[27m");
+/// The glyph displayed for spaces with `--show-whitespace`.
+const SPACE_GLYPH: char = '·';
+/// The glyph displayed for no-break spaces with `--show-whitespace`.
+const NO_BREAK_SPACE_GLYPH: char = '⍽';
+/// The glyph displayed for the other Unicode spaces with `--show-whitespace`.
+const UNICODE_SPACE_GLYPH: char = '␣';
+/// The line printed after a file without a trailing newline, as `diff` does.
+const NO_NEWLINE_MARKER: &str = "\\ No newline at end of file";
+
+/// A piece of a line's content that is rendered with a single style.
+struct Span {
+    /// The byte range of the span within the original line.
+    range: Range<usize>,
+    /// The syntax highlighting style of the span, if syntax highlighting is enabled.
+    style: Option<Style>,
+    /// Whether the span is part of a search match.
+    is_match: bool,
+    /// Whether the span is part of the whitespace at the end of the line.
+    is_trailing_whitespace: bool,
+}
 
 pub struct LineProcessor<'a> {
     /// Field definitions
@@ -20,12 +60,32 @@ pub struct LineProcessor<'a> {
     pub cli: &'a Cli,
     /// The current line number of the file being processed.
     pub file_line_number: usize,
+    /// The name of the file being processed.
+    pub file_name: String,
+    /// The number of lines of the current file that matched the search term.
+    pub matching_line_count: usize,
+    /// The byte offset of the current line within its file.
+    pub line_byte_offset: usize,
+    /// Whether the lines being processed keep bytes that are not valid UTF-8 as raw bytes.
+    pub has_raw_bytes: bool,
+    /// Whether the last line displayed lacks its trailing newline, so the next output would be glued onto it.
+    pub is_line_unterminated: bool,
+    /// The number of bidirectional control characters found by `--check-bidi` in all files.
+    pub bidi_control_count: usize,
+    /// The number of lines of the current file ending with `\r\n`.
+    pub crlf_line_count: usize,
+    /// The number of lines of the current file ending with `\n` alone.
+    pub lf_line_count: usize,
     /// The number of consecutive blank lines encountered.
     pub number_of_consecutive_blank_lines: usize,
     /// The syntax set for syntax highlighting.
     pub syntax_set: SyntaxSet,
     /// The theme set for syntax highlighting.
     pub theme_set: ThemeSet,
+    /// The parse and highlight state carried from line to line of the file being processed.
+    pub syntax_state: Option<(ParseState, HighlightState)>,
+    /// Formats the timestamps of the lines, if `--timestamp` is set.
+    pub timestamper: Option<Timestamper>,
 }
 
 impl<'a> LineProcessor<'a> {
@@ -33,100 +93,207 @@ impl<'a> LineProcessor<'a> {
     ///
     /// Initializes syntax and theme sets for syntax highlighting and sets the initial state for line processing.
     pub fn new(cli: &'a Cli) -> Self {
+        // Relative timestamps count from before the slow loading of the syntax sets
+        let timestamper = cli
+            .timestamp
+            .as_ref()
+            .map(|format| Timestamper::new(format.as_deref(), cli.timestamp_relative));
         let syntax_set = SyntaxSet::load_defaults_newlines(); // Initialize syntax set
         let theme_set = ThemeSet::load_defaults(); // Initialize theme set
         LineProcessor {
             cli,
-            file_line_number: 1,
+            file_line_number: cli.starting_line_number,
+            file_name: String::new(),
+            matching_line_count: 0,
+            line_byte_offset: 0,
+            has_raw_bytes: false,
+            is_line_unterminated: false,
+            bidi_control_count: 0,
+            crlf_line_count: 0,
+            lf_line_count: 0,
             number_of_consecutive_blank_lines: 0,
             syntax_set,
             theme_set,
+            syntax_state: None,
+            timestamper,
         }
     }
 
-    /// Checks if the given character is a non-printing character.
-    fn is_nonprinting_char(&self, c: char) -> bool {
-        c.is_control() && c != '\n' && c != '\t'
+    /// Prepares the processor for a new file, resetting the state that is kept per file.
+    pub fn start_file(&mut self, file_name: &str) {
+        self.file_name = file_name.to_string();
+        self.matching_line_count = 0;
+        self.is_line_unterminated = false;
+        self.has_raw_bytes = false;
+        self.crlf_line_count = 0;
+        self.lf_line_count = 0;
+        // Restart the line numbers if the corresponding CLI option is enabled
+        if self.cli.number_per_file {
+            self.file_line_number = self.cli.starting_line_number;
+        }
+        // Each file is highlighted from its own start
+        self.syntax_state = None;
     }
 
-    /// Checks if the given string contains any non-printing characters.
-    fn contains_nonprinting_chars(&self, s: &str) -> bool {
-        s.chars().any(|c| self.is_nonprinting_char(c))
+    /// Returns the name of the language detected from the name of the current file, or `Plain Text`
+    /// if it is not recognised.
+    ///
+    /// Unlike highlighting, this does not fall back to Rust, so that headers only show the languages
+    /// that were actually detected.
+    pub fn language(&self) -> &str {
+        &detect_syntax(&self.syntax_set, &self.file_name)
+            .unwrap_or_else(|| self.syntax_set.find_syntax_plain_text())
+            .name
+    }
+
+    /// Returns the character ending the lines, `\n` unless the files are split into records.
+    fn line_separator(&self) -> char {
+        self.cli.line_separator() as char
+    }
+    /// Returns the content of the given line, without the separator ending it.
+    fn line_content<'l>(&self, line: &'l str) -> &'l str {
+        line.strip_suffix(self.line_separator()).unwrap_or(line)
+    }
+    /// Checks if the given line is blank.
+    ///
+    /// Like GNU cat, only empty lines are blank, unless lines with only whitespace are too.
+    fn is_blank_line(&self, line: &str) -> bool {
+        if self.cli.whitespace_is_blank {
+            self.line_content(line).trim().is_empty()
+        } else {
+            self.line_content(line).is_empty()
+        }
     }
     /// Handles blank lines based on the specified command-line options.
     fn handle_blank_lines(&mut self, line: &str) {
-        // Blank line does not contain any non-printing characters or tabs
-        // Blank line needs to be empty or contain only whitespace
-        if !self.contains_nonprinting_chars(line) && !line.contains('\t') && line.trim().is_empty()
-        {
+        if self.is_blank_line(line) {
             self.number_of_consecutive_blank_lines += 1;
         } else {
             self.number_of_consecutive_blank_lines = 0;
         }
     }
 
-    /// Increments the file line number.
+    /// Increments the file line number by the line increment.
     fn increment_line_number(&mut self) {
-        self.file_line_number += 1;
+        self.file_line_number += self.cli.line_increment;
     }
-    /// Adds line numbers to the given line.
-    fn add_line_numbers(&mut self, line: &str) -> String {
-        // Format the line number and line
-        let processed_line = format!("{:>6}\t{}", self.file_line_number, line).to_string();
+    /// Formats the line number prefix for the current line.
+    ///
+    /// With the default width, format and separator, this is the `{:>6}\t` prefix of `cat -n`.
+    fn add_line_numbers(&mut self) -> String {
+        // Format the line number
+        let width = self.cli.number_width;
+        let mut line_number = match self.cli.number_format {
+            NumberFormat::Ln => format!("{:<width$}", self.file_line_number),
+            NumberFormat::Rn => format!("{:>width$}", self.file_line_number),
+            NumberFormat::Rz => format!("{:0>width$}", self.file_line_number),
+        };
+        line_number.push_str(&self.cli.number_separator);
         self.increment_line_number();
-        processed_line
+        line_number
     }
-    /// Shows the line numbers for the given line if the corresponding CLI option is enabled.
-    fn show_line_numbers(&mut self, line: &str) -> String {
-        let mut processed_line = line.to_string();
+    /// Returns the timestamp prefix of a line read now, if the corresponding CLI option is enabled.
+    fn show_timestamp(&self) -> String {
+        let mut timestamp = String::new();
+        if let Some(timestamper) = &self.timestamper {
+            // Timestamps form their own gutter, separated by a tab like line numbers
+            timestamp = format!("{}\t", timestamper.now());
+        }
+        timestamp
+    }
+    /// Returns the line number prefix for the given line if the corresponding CLI option is enabled.
+    fn show_line_numbers(&mut self) -> String {
+        let mut line_number = String::new();
         if self.cli.show_line_numbers {
-            processed_line = self.add_line_numbers(line)
+            line_number = self.add_line_numbers();
         }
-        processed_line
+        line_number
     }
-    /// Shows the line numbers for non-blank lines only if the corresponding CLI option is enabled.
+    /// Returns the line number prefix for non-blank lines only if the corresponding CLI option is enabled.
     fn show_non_blank_line_numbers(&mut self, line: &str) -> String {
-        let mut processed_line = line.to_string();
+        let mut line_number = String::new();
 
         // Check if the show_non_blank_line_numbers option is enabled
-        if self.cli.show_non_blank_line_numbers
-        // Check if the line is not empty
-            && (!line.trim().is_empty()
-            // Check if the line contains non-printing characters or tabs
-                || self.contains_nonprinting_chars(line)
-                || line.contains('\t'))
-        {
-            processed_line = self.add_line_numbers(line);
+        if self.cli.show_non_blank_line_numbers && !self.is_blank_line(line) {
+            line_number = self.add_line_numbers();
         }
-        processed_line
+        line_number
+    }
+    /// Returns the line number prefix for the lines matching the regex of `--number-style=pREGEX`.
+    fn show_matching_line_numbers(&mut self, line: &str) -> String {
+        let mut line_number = String::new();
+        if let Some(NumberStyle::Matching(regex)) = &self.cli.number_style {
+            // The line end is not part of the line, so `$` anchors before it
+            if regex.is_match(self.line_content(line).trim_end_matches('\r')) {
+                line_number = self.add_line_numbers();
+            }
+        }
+        line_number
     }
     /// Shows the line end symbol `$` for the given line if the corresponding CLI option is enabled.
     fn show_ends(&self, line: &str) -> String {
         let mut processed_line = line.to_string();
         // Check if the show_ends option is enabled
-        // Check if the line ends with a newline before adding the symbol.
-        if self.cli.show_ends && line.ends_with('\n') {
-            processed_line = line.replace('\n', "$\n");
+        // Check if the line ends with its separator before adding the symbol.
+        if let (true, Some(content)) =
+            (self.cli.show_ends, line.strip_suffix(self.line_separator()))
+        {
+            processed_line = format!("{}${}", content, self.line_separator());
         }
 
         processed_line
     }
+    /// Formats a non-printing character in the notation selected by `--nonprinting-style`.
+    ///
+    /// The default caret notation is the one of GNU cat, such as `^[` for escape.
+    fn format_nonprinting_char(&self, c: char) -> String {
+        if let Some(byte) = self.raw_byte(c) {
+            return match self.cli.nonprinting_style {
+                // Like GNU cat, the meta bit is shown as `M-` before the remaining character
+                NonprintingStyle::Caret => match byte & 0x7f {
+                    low @ (0..=31 | 127) => {
+                        format!("M-{}", self.format_nonprinting_char(low as char))
+                    }
+                    low => format!("M-{}", low as char),
+                },
+                _ => format!("\\x{:02x}", byte),
+            };
+        }
+        match (self.cli.nonprinting_style, c as u32) {
+            (NonprintingStyle::Caret, code @ 0..=31) => format!("^{}", (code as u8 + 64) as char),
+            (NonprintingStyle::Caret, 127) => "^?".to_string(),
+            // For extended characters
+            (NonprintingStyle::Caret, _) => format!("M-{}", c),
+            // The control pictures block follows the order of the control characters
+            (NonprintingStyle::Picture, code @ 0..=31) => {
+                char::from_u32(0x2400 + code).map_or_else(String::new, String::from)
+            }
+            (NonprintingStyle::Picture, 127) => "␡".to_string(),
+            (NonprintingStyle::Escape, 0) => "\\0".to_string(),
+            (NonprintingStyle::Escape, 7) => "\\a".to_string(),
+            (NonprintingStyle::Escape, 8) => "\\b".to_string(),
+            (NonprintingStyle::Escape, 9) => "\\t".to_string(),
+            (NonprintingStyle::Escape, 11) => "\\v".to_string(),
+            (NonprintingStyle::Escape, 12) => "\\f".to_string(),
+            (NonprintingStyle::Escape, 13) => "\\r".to_string(),
+            (NonprintingStyle::Escape, 27) => "\\e".to_string(),
+            // Characters without a picture or an escape are shown in hexadecimal
+            (_, code) => format!("\\x{:02x}", code),
+        }
+    }
+    /// Returns the byte a character stands for if the lines being processed keep raw bytes.
+    fn raw_byte(&self, c: char) -> Option<u8> {
+        raw_byte(c).filter(|_| self.has_raw_bytes)
+    }
     /// Displays non-printing characters for the given string.
     fn display_nonprinting_chars(&self, s: &str) -> String {
         let mut displayed = String::new();
 
         for c in s.chars() {
             // Check if the character is a non-printing character
-            if c.is_control() && c != '\n' && c != '\t' {
+            if (c.is_control() || self.raw_byte(c).is_some()) && c != '\n' && c != '\t' {
                 // Handle non-printing characters
-                if c as u32 <= 31 {
-                    displayed.push_str(&format!("^{}", (c as u8 + 64) as char));
-                } else if c as u32 == 127 {
-                    displayed.push_str("^?");
-                } else {
-                    // For extended characters
-                    displayed.push_str(&format!("M-{}", c));
-                }
+                displayed.push_str(&self.format_nonprinting_char(c));
             } else {
                 // Handle printable characters
                 displayed.push(c);
@@ -135,6 +302,38 @@ impl<'a> LineProcessor<'a> {
 
         displayed
     }
+    /// Returns the line ending output for a line ending with `line_ending`, converted by `--eol`.
+    ///
+    /// The carriage return of a `\r\n` ending is shown as `^M` with `-E`, like GNU cat, or with `-v`,
+    /// in the notation selected by `--nonprinting-style`.
+    fn show_line_ending(&self, line_ending: &str) -> String {
+        let line_ending = match (self.cli.eol, line_ending.is_empty()) {
+            (LineEnding::Lf, false) => "\n",
+            (LineEnding::Crlf, false) => "\r\n",
+            _ => line_ending,
+        };
+        match line_ending.strip_suffix('\n') {
+            Some("\r") if self.cli.show_ends => format!("{}\n", self.format_nonprinting_char('\r')),
+            Some("\r") => self.show_nonprinting(line_ending),
+            _ => line_ending.to_string(),
+        }
+    }
+    /// Counts the line endings of the current file, to detect files with mixed line endings.
+    fn count_line_ending(&mut self, line: &str) {
+        // Only lines can end with `\r\n`, not records
+        if self.line_separator() != '\n' {
+            return;
+        }
+        if line.ends_with("\r\n") {
+            self.crlf_line_count += 1;
+        } else if line.ends_with('\n') {
+            self.lf_line_count += 1;
+        }
+    }
+    /// Checks if the current file has lines ending with `\r\n` and lines ending with `\n` alone.
+    pub fn has_mixed_line_endings(&self) -> bool {
+        self.crlf_line_count > 0 && self.lf_line_count > 0
+    }
     /// Shows non-printing characters for the given line if the corresponding CLI option is enabled.
     fn show_nonprinting(&self, line: &str) -> String {
         let mut processed_line = line.to_string();
@@ -143,99 +342,358 @@ impl<'a> LineProcessor<'a> {
         }
         processed_line
     }
-    /// Shows tabs as `^I` for the given line if the corresponding CLI option is enabled.
+    /// Shows tabs as `^I`, or in the notation selected by `--nonprinting-style`, for the given line if the corresponding CLI option is enabled.
     fn show_tabs(&self, line: &str) -> String {
         let mut processed_line = line.to_string();
         if self.cli.show_tabs {
-            processed_line = line.replace('\t', "^I");
+            processed_line = line.replace('\t', &self.format_nonprinting_char('\t'));
         }
         processed_line
     }
-    /// Highlights the syntax of the given line if the corresponding CLI option is enabled.
-    fn highlight_line(&self, syntax_set: &SyntaxSet, theme: &Theme, line: &str) -> String {
-        // Initialize the highlighter
-        let mut h = match syntax_set.find_syntax_by_extension("rs") {
-            Some(syntax) => HighlightLines::new(syntax, theme),
-            None => {
-                // Handle the error, e.g., use a default syntax
-                let default_syntax = syntax_set.find_syntax_plain_text();
-                HighlightLines::new(default_syntax, theme)
+    /// Shows the given range of the content as `-v` does, and if the corresponding CLI option is
+    /// enabled, shows its invisible and bidirectional control characters and its homoglyphs as
+    /// their code points, such as `<U+202E>`.
+    ///
+    /// Homoglyphs are found in the whole content, as a word can span several ranges.
+    fn show_unicode(&self, content: &str, range: Range<usize>) -> String {
+        if !self.cli.show_unicode {
+            return self.show_nonprinting(&content[range]);
+        }
+        let homoglyphs = unicode::find_homoglyphs(content);
+        let mut processed_text = String::new();
+        let mut run_start = range.start;
+
+        for (index, c) in content[range.clone()].char_indices() {
+            let index = range.start + index;
+            if unicode::is_invisible(c) || homoglyphs.contains(&index) {
+                // The characters in between are left to `-v`, which never escapes these ones
+                processed_text.push_str(&self.show_nonprinting(&content[run_start..index]));
+                let (start, end) = UNICODE_HIGHLIGHT;
+                processed_text.push_str(&format!("{}<U+{:04X}>{}", start, c as u32, end));
+                run_start = index + c.len_utf8();
             }
-        };
+        }
+        processed_text.push_str(&self.show_nonprinting(&content[run_start..range.end]));
+        processed_text
+    }
+    /// Reports the bidirectional control characters of the given line on stderr if the corresponding CLI option is enabled.
+    fn check_bidi(&mut self, line: &str) {
+        if !self.cli.check_bidi {
+            return;
+        }
+        for (index, c) in line.char_indices() {
+            if unicode::is_bidi_control(c) {
+                eprintln!(
+                    "rustcat: {}: bidirectional control character U+{:04X} at byte {}",
+                    self.file_name,
+                    c as u32,
+                    self.line_byte_offset + index
+                );
+                self.bidi_control_count += 1;
+            }
+        }
+    }
+    /// Shows spaces and Unicode spaces as visible glyphs for the given text if the corresponding CLI option is enabled.
+    fn show_whitespace(&self, text: &str) -> String {
+        let mut processed_text = text.to_string();
+        if self.cli.show_whitespace {
+            processed_text = text
+                .chars()
+                .map(|c| match c {
+                    ' ' => SPACE_GLYPH,
+                    '\u{a0}' | '\u{2007}' | '\u{202f}' => NO_BREAK_SPACE_GLYPH,
+                    // Tabs and line breaks are shown by `-T` and `-E`
+                    c if c.is_whitespace() && !c.is_ascii() && !c.is_control() => {
+                        UNICODE_SPACE_GLYPH
+                    }
+                    c => c,
+                })
+                .collect();
+        }
+        processed_text
+    }
+    /// Expands the tabs of the given text, displayed from `column`, if the corresponding CLI options are enabled.
+    ///
+    /// `column` is advanced past the text, so that the spans of a line are expanded one after the other.
+    fn expand_tabs(&self, text: &str, column: &mut usize) -> String {
+        let mut processed_text = text.to_string();
+        if self.cli.tabs.is_some() || self.cli.tab_arrow {
+            let default_stops = TabStops::Every(DEFAULT_TAB_WIDTH);
+            let stops = self.cli.tabs.as_ref().unwrap_or(&default_stops);
+            processed_text = stops.expand(text, column, self.cli.tab_arrow);
+        }
+        processed_text
+    }
+    /// Highlights the syntax of the given line and returns the styled byte ranges of its content.
+    ///
+    /// The parse state is kept between lines so that constructs spanning several lines,
+    /// such as block comments, are highlighted with the context of the whole file.
+    fn highlight_line(
+        &mut self,
+        line: &str,
+        content_len: usize,
+    ) -> Vec<(Option<Style>, Range<usize>)> {
+        let highlighter = Highlighter::new(&self.theme_set.themes["base16-ocean.dark"]);
+        // Initialize the highlighter at the start of each file
+        let (parse_state, highlight_state) = self.syntax_state.get_or_insert_with(|| {
+            let syntax = find_syntax(&self.syntax_set, &self.file_name);
+            (
+                ParseState::new(syntax),
+                HighlightState::new(&highlighter, ScopeStack::new()),
+            )
+        });
         // Highlight the line
-        match h.highlight_line(line, syntax_set) {
-            Ok(ranges) => syntect::util::as_24_bit_terminal_escaped(&ranges[..], false),
+        match parse_state.parse_line(line, &self.syntax_set) {
+            Ok(ops) => {
+                let mut styled_ranges = Vec::new();
+                let mut start = 0;
+                // Convert the highlighted pieces into byte ranges, dropping the line ending
+                for (style, text) in
+                    HighlightIterator::new(highlight_state, &ops, line, &highlighter)
+                {
+                    let end = (start + text.len()).min(content_len);
+                    if start < end {
+                        styled_ranges.push((Some(style), start..end));
+                    }
+                    start += text.len();
+                }
+                styled_ranges
+            }
             Err(e) => {
                 eprintln!("Error: {}", e);
-                line.to_string()
+                vec![(None, 0..content_len)]
             }
         }
     }
     /// Highlights the syntax of the given line if the corresponding CLI option is enabled.
-    fn highlight_syntax(&self, line: &str) -> String {
-        let mut processed_line = line.to_string();
+    ///
+    /// Returns the byte ranges of the line content (without the line ending) together with their style.
+    /// Every line of a file must be passed through here, including the ones that are not displayed.
+    fn highlight_syntax(&mut self, line: &str) -> Vec<(Option<Style>, Range<usize>)> {
+        let content_len = self.line_content(line).len();
+        let mut styled_ranges = vec![(None, 0..content_len)];
         if self.cli.highlight_syntax {
-            processed_line = self.highlight_line(
-                &self.syntax_set,
-                &self.theme_set.themes["base16-ocean.dark"],
-                line,
-            );
+            styled_ranges = self.highlight_line(line, content_len);
         }
-        processed_line
+        styled_ranges
     }
-    /// Highlights the search term in the given line.
-    fn highlight_search_term(line: &str, search_term: &str) -> String {
-        let mut highlighted_line = String::new();
-        let mut start = 0;
-
-        while let Some(position) = line[start..].find(search_term) {
-            // Add the text before the search term
-            highlighted_line.push_str(&line[start..start + position]);
+    /// Splits the styled ranges of a line at the boundaries of the search matches.
+    ///
+    /// Each resulting span keeps the syntax style of the range it was cut from and records
+    /// whether it lies inside a search match.
+    fn split_at_search_matches(
+        styled_ranges: &[(Option<Style>, Range<usize>)],
+        matches: &[Range<usize>],
+    ) -> Vec<Span> {
+        let mut spans = Vec::new();
 
-            // Add the search term with highlighting
-            highlighted_line.push_str("\x1b[33m"); // Red color
-            highlighted_line.push_str(search_term);
-            highlighted_line.push_str("\x1b[0m"); // Reset color
+        for (style, range) in styled_ranges {
+            // Collect the match boundaries that fall inside this range
+            let mut boundaries = vec![range.start, range.end];
+            for search_match in matches {
+                for boundary in [search_match.start, search_match.end] {
+                    if range.start < boundary && boundary < range.end {
+                        boundaries.push(boundary);
+                    }
+                }
+            }
+            boundaries.sort_unstable();
+            boundaries.dedup();
 
-            // Update the start position
-            start += position + search_term.len();
+            for pair in boundaries.windows(2) {
+                let is_match = matches.iter().any(|search_match| {
+                    search_match.start <= pair[0] && pair[0] < search_match.end
+                });
+                spans.push(Span {
+                    range: pair[0]..pair[1],
+                    style: *style,
+                    is_match,
+                    is_trailing_whitespace: false,
+                });
+            }
         }
+        spans
+    }
+    /// Splits the spans at the start of the whitespace ending the line, `trailing_start`, and marks
+    /// the spans after it as trailing whitespace.
+    fn split_at_trailing_whitespace(spans: Vec<Span>, trailing_start: usize) -> Vec<Span> {
+        let mut split_spans = Vec::new();
 
-        // Add any remaining text after the last occurrence
-        highlighted_line.push_str(&line[start..]);
+        for span in spans {
+            if span.range.start < trailing_start && trailing_start < span.range.end {
+                split_spans.push(Span {
+                    range: span.range.start..trailing_start,
+                    ..span
+                });
+                split_spans.push(Span {
+                    range: trailing_start..span.range.end,
+                    is_trailing_whitespace: true,
+                    ..span
+                });
+            } else {
+                split_spans.push(Span {
+                    is_trailing_whitespace: span.range.start >= trailing_start,
+                    ..span
+                });
+            }
+        }
+        split_spans
+    }
+    /// Renders the content of a line, applying the character transformations and styles of each span.
+    fn render_spans(&self, content: &str, spans: &[Span]) -> String {
+        let mut rendered = String::new();
+        let mut is_styled = false;
+        // Tab stops are counted from the start of the content, after the gutter
+        let mut column = 0;
 
-        highlighted_line
+        for span in spans {
+            // Transform the text of the span itself, never the escapes around it
+            let text = self.show_unicode(content, span.range.clone());
+            let text = self.show_tabs(&self.show_whitespace(&text));
+            let text = self.expand_tabs(&text, &mut column);
+            if span.is_match {
+                rendered.push_str(SEARCH_HIGHLIGHT);
+                rendered.push_str(&text);
+                rendered.push_str(RESET);
+            } else if span.is_trailing_whitespace {
+                rendered.push_str(TRAILING_WHITESPACE_HIGHLIGHT);
+                rendered.push_str(&text);
+                rendered.push_str(RESET);
+            } else if let Some(style) = span.style {
+                rendered.push_str(&syntect::util::as_24_bit_terminal_escaped(
+                    &[(style, &text)],
+                    false,
+                ));
+                is_styled = true;
+            } else {
+                rendered.push_str(&text);
+            }
+        }
+        // Keep syntax colours from leaking into the line end and the next line
+        if is_styled {
+            rendered.push_str(RESET);
+        }
+        rendered
     }
-    /// Checks if the number of consecutive blanks lines has exceeded 1. If so, then it decrements the counter and returns `true` to skip the current line.
-    pub fn is_skipping_blank_line(&mut self) -> bool {
-        if self.cli.squeeze_blank && self.number_of_consecutive_blank_lines > 1 {
-            self.number_of_consecutive_blank_lines -= 1;
-            true
-        } else {
-            false
+    /// Checks if the number of consecutive blanks lines has exceeded the number kept by `--squeeze-blank`,
+    /// and returns `true` to skip the current line if so.
+    pub fn is_skipping_blank_line(&self) -> bool {
+        self.cli
+            .squeeze_blank
+            .is_some_and(|kept| self.number_of_consecutive_blank_lines > kept)
+    }
+    /// Checks if the line at the given 0-based index is selected by the `--lines`, `--head`
+    /// and `--tail` options.
+    fn is_line_selected(&self, index: usize, total: usize) -> bool {
+        (self.cli.lines.is_empty() || selection::is_selected(&self.cli.lines, index, total))
+            && self.cli.head.is_none_or(|count| index < count)
+            && self.cli.tail.is_none_or(|count| index + count >= total)
+    }
+    /// Advances the line number over lines that were not read, as if they had been displayed.
+    pub fn skip_line_numbers(&mut self, count: usize) {
+        if self.cli.show_line_numbers {
+            self.file_line_number += count * self.cli.line_increment;
         }
     }
+    /// Returns the file name prefix for the current line if the corresponding CLI option is enabled.
+    fn show_file_name(&self) -> String {
+        let mut file_name = String::new();
+        if self.cli.with_filename {
+            file_name = format!("{}{}{}:", FILE_NAME_HIGHLIGHT, self.file_name, RESET);
+        }
+        file_name
+    }
+    /// Returns the line number prefix for the given line and advances the line number
+    /// exactly as `-n`, `-b` or `--number-style` would, or the byte offset prefix with `--offset`.
+    fn number_line(&mut self, line: &str) -> String {
+        // Byte offsets are displayed instead of line numbers
+        if self.cli.show_byte_offset {
+            return format!("{:>8}\t", self.line_byte_offset);
+        }
+        let mut line_number = self.show_non_blank_line_numbers(line);
+        line_number.push_str(&self.show_line_numbers());
+        line_number.push_str(&self.show_matching_line_numbers(line));
+        line_number
+    }
     /// Processes the given line based on the specified command-line options.
-    fn process_line(&mut self, line: &str) -> String {
-        let mut processed_line = line.to_string();
+    ///
+    /// `styled_ranges` are the syntax highlighting styles of the line and `matches` are the byte
+    /// ranges of the search matches within the original line.
+    fn process_line(
+        &mut self,
+        line: &str,
+        styled_ranges: &[(Option<Style>, Range<usize>)],
+        matches: &[Range<usize>],
+    ) -> String {
+        // Separate the content from the line ending, including the carriage return of `\r\n`
+        let content = match line.strip_suffix("\r\n") {
+            Some(content) if self.line_separator() == '\n' => content,
+            _ => self.line_content(line),
+        };
+        let line_ending = &line[content.len()..];
 
-        processed_line = self.show_non_blank_line_numbers(&processed_line);
-        processed_line = self.show_nonprinting(&processed_line);
-        processed_line = self.show_line_numbers(&processed_line);
-        processed_line = self.show_ends(&processed_line);
-        processed_line = self.show_tabs(&processed_line);
-        processed_line = self.highlight_syntax(&processed_line);
-        processed_line
+        let mut processed_line = self.show_file_name();
+        processed_line.push_str(&self.show_timestamp());
+        processed_line.push_str(&self.number_line(line));
+        let mut spans = Self::split_at_search_matches(styled_ranges, matches);
+        // The carriage return is rendered with the line ending, not the content
+        spans.retain_mut(|span| {
+            span.range.end = span.range.end.min(content.len());
+            span.range.start < span.range.end
+        });
+        if self.cli.show_whitespace {
+            spans = Self::split_at_trailing_whitespace(spans, content.trim_end().len());
+        }
+        processed_line.push_str(&self.render_spans(content, &spans));
+        processed_line.push_str(&self.show_line_ending(line_ending));
+        self.show_ends(&processed_line)
+    }
+    /// Processes the search matches of the given line for `--only-matching`, one output line per match.
+    fn process_only_matching(&mut self, line: &str, matches: &[Range<usize>]) -> String {
+        let content = self.line_content(line);
+        // All matches of a line share its file name and line number
+        let mut prefix = self.show_file_name();
+        prefix.push_str(&self.show_timestamp());
+        prefix.push_str(&self.number_line(line));
+        let mut processed_lines = String::new();
+
+        for search_match in matches {
+            let mut processed_line = prefix.clone();
+            // Show the 1-based byte column of the match if the corresponding CLI option is enabled
+            if self.cli.show_column {
+                processed_line.push_str(&format!("{}:", search_match.start + 1));
+            }
+            let text = self.show_unicode(content, search_match.clone());
+            let text = self.show_tabs(&self.show_whitespace(&text));
+            processed_line.push_str(&self.expand_tabs(&text, &mut 0));
+            processed_line.push(self.line_separator());
+            processed_lines.push_str(&self.show_ends(&processed_line));
+        }
+        processed_lines
     }
 
-    /// Processes,searches, and displays the given lines based on the specified command-line options.
-    fn process_and_display_lines_search(
+    /// Processes, searches if a pattern is given, and displays the given lines based on the specified command-line options.
+    fn process_and_display_lines_with_pattern(
         &mut self,
         lines: &[String],
         handle: &mut io::StdoutLock<'_>,
-        term: &str,
-    ) {
-        for line in lines {
+        pattern: Option<&SearchPattern>,
+        is_selecting: bool,
+    ) -> io::Result<()> {
+        let mut next_line_byte_offset = self.line_byte_offset;
+
+        for (index, line) in lines.iter().enumerate() {
+            // Track the byte offset of every line, including the ones that are not displayed
+            self.line_byte_offset = next_line_byte_offset;
+            next_line_byte_offset += if self.has_raw_bytes {
+                encode_raw_bytes(line).len()
+            } else {
+                line.len()
+            };
+            // Highlight every line so that the syntax state follows the whole file
+            let styled_ranges = self.highlight_syntax(line);
+            self.count_line_ending(line);
+            self.check_bidi(line);
             // Handle blank lines
             self.handle_blank_lines(line);
 
@@ -243,48 +701,114 @@ impl<'a> LineProcessor<'a> {
             if self.is_skipping_blank_line() {
                 continue;
             }
-            // Check if the line contains the search term
-            if line.contains(term) {
-                let mut processed_line = self.process_line(line);
-                processed_line = Self::highlight_search_term(&processed_line, term);
-                if let Err(e) = write!(handle, "{}", processed_line) {
-                    eprintln!("Error writing to stdout: {}", e);
-                    break;
+            // Number the lines outside of the selected ranges without printing them
+            if is_selecting && !self.is_line_selected(index, lines.len()) {
+                self.number_line(line);
+                continue;
+            }
+            // Find the matches in the original line so that they never overlap escapes or markers
+            let content = self.line_content(line);
+            let processed_line = match pattern.map(|pattern| pattern.find_line_matches(content)) {
+                // Display the line without searching
+                None => self.process_line(line, &styled_ranges, &[]),
+                // Display the line if it matches the search pattern
+                Some(Some(matches)) => {
+                    self.matching_line_count += 1;
+                    // Only the file names are displayed when listing files
+                    if self.cli.files_with_matches || self.cli.files_without_match {
+                        self.number_line(line);
+                        continue;
+                    }
+                    if self.cli.only_matching {
+                        self.process_only_matching(line, &matches)
+                    } else {
+                        self.process_line(line, &styled_ranges, &matches)
+                    }
                 }
+                // Number the line without printing it so that search stays a pure filter
+                Some(None) => {
+                    self.number_line(line);
+                    continue;
+                }
+            };
+            if self.has_raw_bytes {
+                handle.write_all(&encode_raw_bytes(&processed_line))?;
             } else {
-                self.increment_line_number();
-                continue;
+                write!(handle, "{}", processed_line)?;
             }
+            self.is_line_unterminated = !processed_line.ends_with(self.line_separator());
         }
+        self.line_byte_offset = next_line_byte_offset;
+        Ok(())
     }
-    /// Processes and displays the given lines based on the specified command-line options.
-    fn process_and_display_lines_no_search(
+    /// Processes and displays the given lines, applying the line selection options if `is_selecting` is set.
+    /// Decides whether to search or not.
+    fn process_and_display_lines_selecting(
         &mut self,
         lines: &[String],
         handle: &mut io::StdoutLock<'_>,
-    ) {
-        for line in lines {
-            // Handle blank lines
-            self.handle_blank_lines(line);
-            // Skip the line if necessary
-            if self.is_skipping_blank_line() {
-                continue;
-            }
-            // Process and display the line
-            let processed_line = self.process_line(line);
-            if let Err(e) = write!(handle, "{}", processed_line) {
-                eprintln!("Error writing to stdout: {}", e);
-                break;
-            }
+        is_selecting: bool,
+    ) -> io::Result<()> {
+        // Check if the search option is enabled
+        if let Some(term) = &self.cli.search {
+            let fuzzy_threshold = self.cli.fuzzy.then_some(self.cli.fuzzy_threshold);
+            let pattern = SearchPattern::new(term, self.cli.word_regexp, fuzzy_threshold);
+            self.process_and_display_lines_with_pattern(lines, handle, Some(&pattern), is_selecting)
+        } else {
+            self.process_and_display_lines_with_pattern(lines, handle, None, is_selecting)
+        }
+    }
+    /// Ends the output of a file whose last displayed line lacks a trailing newline, if the corresponding CLI options are enabled.
+    ///
+    /// `--ensure-newline` terminates the line, and `--mark-missing-newline` also prints a marker line
+    /// like `diff` does.
+    pub fn display_end_of_file(&mut self, handle: &mut io::StdoutLock<'_>) -> io::Result<()> {
+        if !self.is_line_unterminated || !(self.cli.ensure_newline || self.cli.mark_missing_newline)
+        {
+            return Ok(());
         }
+        write!(handle, "{}", self.line_separator())?;
+        if self.cli.mark_missing_newline {
+            write!(handle, "{}{}", NO_NEWLINE_MARKER, self.line_separator())?;
+        }
+        self.is_line_unterminated = false;
+        Ok(())
     }
     /// Processes and displays the given lines based on the specified command-line options. Decides whether to search or not.
     pub fn process_and_display_lines(&mut self, lines: &[String], handle: &mut io::StdoutLock<'_>) {
-        // Check if the search option is enabled
-        if let Some(term) = &self.cli.search {
-            self.process_and_display_lines_search(lines, handle, term);
-        } else {
-            self.process_and_display_lines_no_search(lines, handle);
+        if let Err(e) = self.process_and_display_lines_selecting(lines, handle, true) {
+            eprintln!("Error writing to stdout: {}", e);
         }
     }
+    /// Processes and displays lines appended to a followed file.
+    ///
+    /// The line selection options only apply to the lines that were in the file at the start,
+    /// so every appended line is displayed, still subject to searching and squeezing.
+    ///
+    /// Errors writing to stdout are returned, so that the caller stops reading more lines.
+    pub fn process_and_display_appended_lines(
+        &mut self,
+        lines: &[String],
+        handle: &mut io::StdoutLock<'_>,
+    ) -> io::Result<()> {
+        self.process_and_display_lines_selecting(lines, handle, false)
+    }
+}
+
+/// Detects the syntax of a file from its name, or from the name of the member for archive members.
+fn detect_syntax<'s>(syntax_set: &'s SyntaxSet, file_name: &str) -> Option<&'s SyntaxReference> {
+    let name = Path::new(archive::content_name(file_name));
+    [name.extension(), name.file_name()]
+        .into_iter()
+        .flatten()
+        .find_map(|name| syntax_set.find_syntax_by_extension(&name.to_string_lossy()))
+}
+
+/// Finds the syntax a file is highlighted with, which is Rust if none is detected from its name.
+fn find_syntax<'s>(syntax_set: &'s SyntaxSet, file_name: &str) -> &'s SyntaxReference {
+    detect_syntax(syntax_set, file_name)
+        // Fall back to Rust, the default syntax of RustCat
+        .or_else(|| syntax_set.find_syntax_by_extension("rs"))
+        // Handle the error, e.g., use a default syntax
+        .unwrap_or_else(|| syntax_set.find_syntax_plain_text())
 }
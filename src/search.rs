@@ -0,0 +1,212 @@
+//! # Search Module for RustCat
+//!
+//! This module contains the matching logic behind RustCat's search functionality.
+//! Matches are always computed on the original text of a line, before any line numbers,
+//! non-printing notation or syntax highlighting escapes are added, and are reported as
+//! byte ranges so that the display code can render them on top of other styling.
+//!
+//! Besides exact matching, a fuzzy mode scores each line by how well the search term
+//! matches it as a subsequence, so that lines can be found from an approximate query.
+use std::ops::Range;
+
+/// The score of each character of the search term found in the line.
+const FUZZY_MATCH_SCORE: i64 = 16;
+/// The bonus for a character that directly follows the previously matched character.
+const FUZZY_CONSECUTIVE_BONUS: i64 = 8;
+/// The bonus for a character that starts a word.
+const FUZZY_WORD_START_BONUS: i64 = 8;
+/// The penalty for each character skipped between two matched characters.
+const FUZZY_GAP_PENALTY: i64 = 1;
+
+/// A search term together with the options that control how it matches.
+pub struct SearchPattern<'a> {
+    /// The text to search for.
+    pub term: &'a str,
+    /// Only accept matches that form whole words.
+    pub whole_word: bool,
+    /// The minimum score, in percent, of a fuzzy match. Exact matching is used if this is `None`.
+    pub fuzzy_threshold: Option<u8>,
+}
+
+impl<'a> SearchPattern<'a> {
+    /// Constructs a new `SearchPattern`.
+    pub fn new(term: &'a str, whole_word: bool, fuzzy_threshold: Option<u8>) -> Self {
+        SearchPattern {
+            term,
+            whole_word,
+            fuzzy_threshold,
+        }
+    }
+
+    /// Finds the matches of the pattern in a line, or returns `None` if the line should not be displayed.
+    ///
+    /// An empty search term selects every line without highlighting anything.
+    pub fn find_line_matches(&self, text: &str) -> Option<Vec<Range<usize>>> {
+        // Fuzzy matches are selected by their score rather than by their presence
+        if let Some(threshold) = self.fuzzy_threshold {
+            if self.term.is_empty() {
+                return Some(Vec::new());
+            }
+            return find_fuzzy_match(text, self.term)
+                .filter(|fuzzy_match| fuzzy_match.score >= threshold)
+                .map(|fuzzy_match| fuzzy_match.ranges);
+        }
+        let matches = self.find_matches(text);
+        if self.term.is_empty() || !matches.is_empty() {
+            Some(matches)
+        } else {
+            None
+        }
+    }
+
+    /// Finds every non-overlapping match of the pattern in `text`.
+    ///
+    /// The returned byte ranges are sorted and always fall on `char` boundaries of `text`.
+    /// An empty search term matches nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let pattern = SearchPattern::new("id", true, None);
+    /// assert_eq!(pattern.find_matches("valid id"), vec![6..8]);
+    /// ```
+    pub fn find_matches(&self, text: &str) -> Vec<Range<usize>> {
+        let mut matches = Vec::new();
+        // An empty term would match at every position without advancing
+        if self.term.is_empty() {
+            return matches;
+        }
+        let mut start = 0;
+
+        while let Some(position) = text[start..].find(self.term) {
+            let match_start = start + position;
+            let match_end = match_start + self.term.len();
+            if !self.whole_word || is_whole_word(text, match_start..match_end) {
+                // Record the match and continue searching after it
+                matches.push(match_start..match_end);
+                start = match_end;
+            } else {
+                // Retry from the next character, the term may still occur as a word inside this one
+                start = match_start + text[match_start..].chars().next().map_or(1, char::len_utf8);
+            }
+        }
+        matches
+    }
+}
+
+/// Checks if the given character can be part of a word.
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Checks if the given range of `text` is neither preceded nor followed by a word character.
+fn is_whole_word(text: &str, range: Range<usize>) -> bool {
+    let before = text[..range.start].chars().next_back();
+    let after = text[range.end..].chars().next();
+    !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+}
+
+/// The best fuzzy match of a search term in a line.
+#[derive(Debug, PartialEq)]
+pub struct FuzzyMatch {
+    /// How well the term matches, from 0 to 100.
+    pub score: u8,
+    /// The byte ranges of the matched characters, with adjacent characters merged.
+    pub ranges: Vec<Range<usize>>,
+}
+
+/// Finds the best match of `term` as a case-insensitive subsequence of `text`.
+///
+/// Every occurrence of the first character of the term is tried as a starting point and the rest
+/// of the term is matched greedily from there. Matches score higher when their characters are
+/// consecutive or start words, and lower for every character skipped in between. The score is
+/// normalized so that a term found as a whole word scores 100.
+///
+/// Returns `None` if `term` is not a subsequence of `text`.
+///
+/// # Examples
+///
+/// ```
+/// let fuzzy_match = find_fuzzy_match("config.yaml", "cfg").unwrap();
+/// assert_eq!(fuzzy_match.ranges, vec![0..1, 3..4, 5..6]);
+/// ```
+pub fn find_fuzzy_match(text: &str, term: &str) -> Option<FuzzyMatch> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let term: Vec<char> = term.chars().collect();
+    let first = *term.first()?;
+    let mut best: Option<(i64, Vec<usize>)> = None;
+
+    for start in 0..chars.len() {
+        if !eq_ignore_case(chars[start].1, first) {
+            continue;
+        }
+        // Greedily match the rest of the term after this starting point
+        let mut positions = vec![start];
+        let mut next = start + 1;
+        for &c in &term[1..] {
+            match (next..chars.len()).find(|&i| eq_ignore_case(chars[i].1, c)) {
+                Some(i) => {
+                    positions.push(i);
+                    next = i + 1;
+                }
+                None => break,
+            }
+        }
+        if positions.len() < term.len() {
+            // Later starting points cannot match the term either
+            break;
+        }
+        let score = fuzzy_score(&chars, &positions);
+        if best
+            .as_ref()
+            .is_none_or(|(best_score, _)| score > *best_score)
+        {
+            best = Some((score, positions));
+        }
+    }
+
+    let (score, positions) = best?;
+    let max_score = term.len() as i64 * (FUZZY_MATCH_SCORE + FUZZY_CONSECUTIVE_BONUS);
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for position in positions {
+        let (byte_start, c) = chars[position];
+        let byte_end = byte_start + c.len_utf8();
+        // Merge the characters that are adjacent in the line
+        match ranges.last_mut() {
+            Some(last) if last.end == byte_start => last.end = byte_end,
+            _ => ranges.push(byte_start..byte_end),
+        }
+    }
+    Some(FuzzyMatch {
+        score: (score.clamp(0, max_score) * 100 / max_score) as u8,
+        ranges,
+    })
+}
+
+/// Scores the matched character positions of a fuzzy match.
+fn fuzzy_score(chars: &[(usize, char)], positions: &[usize]) -> i64 {
+    let mut score = 0;
+    let mut previous: Option<usize> = None;
+
+    for &position in positions {
+        score += FUZZY_MATCH_SCORE;
+        // Reward characters that start a word
+        if position == 0 || !is_word_char(chars[position - 1].1) {
+            score += FUZZY_WORD_START_BONUS;
+        }
+        if let Some(previous) = previous {
+            if position == previous + 1 {
+                score += FUZZY_CONSECUTIVE_BONUS;
+            } else {
+                score -= (position - previous - 1) as i64 * FUZZY_GAP_PENALTY;
+            }
+        }
+        previous = Some(position);
+    }
+    score
+}
+
+/// Compares two characters without regard to case.
+fn eq_ignore_case(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
@@ -0,0 +1,113 @@
+//! # Selection Module for RustCat
+//!
+//! This module parses the `START:END` ranges used to display only part of each file.
+//! Both ends are inclusive and 1-based. Either end may be omitted to extend the range to
+//! the start or the end of the file, and a negative value counts back from the end, so
+//! `-20:` selects the last twenty lines.
+use std::ops::Range;
+use std::str::FromStr;
+
+/// One end of a selection range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RangeBound {
+    /// A 1-based position counted from the start of the file.
+    FromStart(usize),
+    /// A position counted back from the end of the file, `FromEnd(1)` being the last one.
+    FromEnd(usize),
+}
+
+impl RangeBound {
+    /// Resolves the bound to a 1-based position in a file with `total` elements.
+    ///
+    /// Positions before the start of the file resolve to 0.
+    fn resolve(self, total: usize) -> usize {
+        match self {
+            RangeBound::FromStart(position) => position,
+            RangeBound::FromEnd(position) => (total + 1).saturating_sub(position),
+        }
+    }
+}
+
+impl FromStr for RangeBound {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (from_end, number) = match s.strip_prefix('-') {
+            Some(number) => (true, number),
+            None => (false, s),
+        };
+        let position = number
+            .parse::<usize>()
+            .map_err(|_| format!("invalid position '{}'", s))?;
+        // Positions are 1-based, so zero is never part of the file
+        match (from_end, position) {
+            (_, 0) => Err(format!("invalid position '{}', positions start at 1", s)),
+            (true, position) => Ok(RangeBound::FromEnd(position)),
+            (false, position) => Ok(RangeBound::FromStart(position)),
+        }
+    }
+}
+
+/// An inclusive range of positions, such as lines, selected with `START:END`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelectionRange {
+    /// The first selected position, or the start of the file if `None`.
+    pub start: Option<RangeBound>,
+    /// The last selected position, or the end of the file if `None`.
+    pub end: Option<RangeBound>,
+}
+
+impl SelectionRange {
+    /// Resolves the range to 0-based indices in a file with `total` elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let last_twenty: SelectionRange = "-20:".parse().unwrap();
+    /// assert_eq!(last_twenty.resolve(100), 80..100);
+    /// ```
+    pub fn resolve(&self, total: usize) -> Range<usize> {
+        let start = self.start.map_or(1, |bound| bound.resolve(total)).max(1);
+        let end = self
+            .end
+            .map_or(total, |bound| bound.resolve(total))
+            .min(total);
+        // An empty range is returned if the end comes before the start
+        (start - 1)..end.max(start - 1)
+    }
+}
+
+impl FromStr for SelectionRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_bound = |bound: &str| match bound {
+            "" => Ok(None),
+            bound => bound.parse::<RangeBound>().map(Some),
+        };
+        match s.split_once(':') {
+            Some((start, end)) => Ok(SelectionRange {
+                start: parse_bound(start)?,
+                end: parse_bound(end)?,
+            }),
+            // A single position selects only itself
+            None => {
+                let position = parse_bound(s)?;
+                if position.is_none() {
+                    return Err("empty range, expected START:END".to_string());
+                }
+                Ok(SelectionRange {
+                    start: position,
+                    end: position,
+                })
+            }
+        }
+    }
+}
+
+/// Checks if the 0-based `index` falls into any of the `ranges` in a file with `total` elements.
+pub fn is_selected(ranges: &[SelectionRange], index: usize, total: usize) -> bool {
+    ranges
+        .iter()
+        .any(|range| range.resolve(total).contains(&index))
+}
@@ -0,0 +1,104 @@
+//! # Tabs Module for RustCat
+//!
+//! This module expands tabs to spaces for `--tabs` and `--tab-arrow`. Tab stops are either set
+//! every N columns, or at a list of columns like the `-t` option of `expand`, in which case a tab
+//! past the last stop is replaced by a single space.
+//!
+//! Columns are counted in display width, so wide characters such as CJK ideographs take two
+//! columns and combining marks none, and they start at the content of each line, after the file
+//! name and line number gutter, so a file keeps its alignment whatever prefixes are displayed.
+use std::str::FromStr;
+use unicode_width::UnicodeWidthChar;
+
+/// The distance between tab stops if only `--tab-arrow` is given.
+pub const DEFAULT_TAB_WIDTH: usize = 8;
+/// The glyph that starts an expanded tab with `--tab-arrow`.
+const TAB_ARROW: char = '→';
+
+/// The tab stops of `--tabs`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TabStops {
+    /// A tab stop every N columns.
+    Every(usize),
+    /// Tab stops at the given 0-based columns, in increasing order.
+    List(Vec<usize>),
+}
+
+impl TabStops {
+    /// Returns the column of the first tab stop after `column`.
+    ///
+    /// Past the last stop of a list, the tab only advances to the next column.
+    fn next_stop(&self, column: usize) -> usize {
+        match self {
+            TabStops::Every(width) => (column / width + 1) * width,
+            TabStops::List(stops) => stops
+                .iter()
+                .copied()
+                .find(|&stop| stop > column)
+                .unwrap_or(column + 1),
+        }
+    }
+
+    /// Expands the tabs of `text`, which is displayed from `column`, and advances `column` past it.
+    ///
+    /// Each tab is replaced by spaces up to the next tab stop, the first of which is an arrow if
+    /// `arrow` is set. Escape sequences, such as the colours of `--show-unicode`, take no columns.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mut column = 0;
+    /// assert_eq!(TabStops::Every(4).expand("a\tb", &mut column, false), "a   b");
+    /// assert_eq!(column, 5);
+    /// ```
+    pub fn expand(&self, text: &str, column: &mut usize, arrow: bool) -> String {
+        let mut expanded = String::new();
+        let mut in_escape = false;
+
+        for c in text.chars() {
+            if in_escape || c == '\x1b' {
+                expanded.push(c);
+                // Escape sequences end with a letter, such as the `m` of colours
+                in_escape = !c.is_ascii_alphabetic();
+            } else if c == '\t' {
+                let stop = self.next_stop(*column);
+                if arrow {
+                    expanded.push(TAB_ARROW);
+                    expanded.push_str(&" ".repeat(stop - *column - 1));
+                } else {
+                    expanded.push_str(&" ".repeat(stop - *column));
+                }
+                *column = stop;
+            } else {
+                expanded.push(c);
+                // Control characters have no width of their own
+                *column += c.width().unwrap_or(0);
+            }
+        }
+        expanded
+    }
+}
+
+impl FromStr for TabStops {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let stops = s
+            .split(',')
+            .map(|stop| {
+                stop.trim()
+                    .parse::<usize>()
+                    .map_err(|_| format!("invalid tab stop '{}'", stop))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        match stops.as_slice() {
+            [0] => Err("tab size must be at least 1".to_string()),
+            [width] => Ok(TabStops::Every(*width)),
+            // Stops must increase, or tabs could not reach them
+            stops if stops.windows(2).all(|pair| pair[0] < pair[1]) => {
+                Ok(TabStops::List(stops.to_vec()))
+            }
+            _ => Err(format!("tab stops '{}' must be in increasing order", s)),
+        }
+    }
+}
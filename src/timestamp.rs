@@ -0,0 +1,128 @@
+//! # Timestamp Module for RustCat
+//!
+//! This module formats the timestamps that `--timestamp` prefixes to output lines, the way `ts`
+//! from moreutils does. Formats use the `strftime` syntax of the C library, extended like `ts`
+//! with `%.S`, `%.s` and `%.T`, which add microseconds to `%S`, `%s` and `%T`.
+//!
+//! Timestamps show the local wall clock time, or with `--timestamp-relative` the time elapsed
+//! since RustCat started, formatted as a time of day.
+use std::ffi::CString;
+use std::mem;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The format of wall clock timestamps if none is given, the one `ts` uses.
+const DEFAULT_FORMAT: &str = "%b %d %H:%M:%S";
+/// The format of relative timestamps if none is given.
+const DEFAULT_RELATIVE_FORMAT: &str = "%H:%M:%S";
+/// The initial size of the buffer `strftime` writes to, doubled until the timestamp fits.
+const INITIAL_BUFFER_SIZE: usize = 64;
+/// The largest buffer tried before giving up on a format whose output is empty.
+const MAX_BUFFER_SIZE: usize = 4096;
+
+/// Formats the time at which lines are read.
+pub struct Timestamper {
+    /// The `strftime` format of the timestamps.
+    format: String,
+    /// Flag to show the time elapsed since `start` instead of the wall clock time.
+    relative: bool,
+    /// The time RustCat started at.
+    start: SystemTime,
+}
+
+impl Timestamper {
+    /// Constructs a new `Timestamper`, using the default format for the mode if `format` is `None`.
+    pub fn new(format: Option<&str>, relative: bool) -> Self {
+        let default_format = if relative {
+            DEFAULT_RELATIVE_FORMAT
+        } else {
+            DEFAULT_FORMAT
+        };
+        Timestamper {
+            format: format.unwrap_or(default_format).to_string(),
+            relative,
+            start: SystemTime::now(),
+        }
+    }
+
+    /// Formats the current time.
+    pub fn now(&self) -> String {
+        let now = SystemTime::now();
+        let time = if self.relative {
+            now.duration_since(self.start)
+        } else {
+            now.duration_since(UNIX_EPOCH)
+        };
+        self.format_time(time.unwrap_or(Duration::ZERO))
+    }
+
+    /// Formats a time given as a duration since the epoch, or since the start if relative.
+    fn format_time(&self, time: Duration) -> String {
+        let seconds = time.as_secs();
+        let micros = time.subsec_micros();
+        // Expand the extensions and `%s` before `strftime`, which does not know about them
+        let mut format = String::new();
+        let mut chars = self.format.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                format.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('s') => format.push_str(&seconds.to_string()),
+                Some('.') => match chars.next_if(|&c| matches!(c, 'S' | 's' | 'T')) {
+                    Some('S') => format.push_str(&format!("%S.{:06}", micros)),
+                    Some('T') => format.push_str(&format!("%T.{:06}", micros)),
+                    Some(_) => format.push_str(&format!("{}.{:06}", seconds, micros)),
+                    None => format.push_str("%."),
+                },
+                Some(next) => {
+                    format.push('%');
+                    format.push(next);
+                }
+                None => format.push('%'),
+            }
+        }
+        strftime(&format, seconds, self.relative)
+    }
+}
+
+/// Formats `seconds` with the C library's `strftime`, as UTC if `utc` is set or as local time.
+fn strftime(format: &str, seconds: u64, utc: bool) -> String {
+    // Formats cannot contain NUL bytes, which would end them early
+    let format = match CString::new(format) {
+        Ok(format) => format,
+        Err(_) => return String::new(),
+    };
+    let time = seconds as libc::time_t;
+    // SAFETY: `tm` is plain data, fully initialized by `gmtime_r` or `localtime_r` before it is read.
+    let mut tm: libc::tm = unsafe { mem::zeroed() };
+    // SAFETY: Both pointers are valid for the duration of the call.
+    unsafe {
+        if utc {
+            libc::gmtime_r(&time, &mut tm);
+        } else {
+            libc::localtime_r(&time, &mut tm);
+        }
+    }
+
+    let mut buffer_size = INITIAL_BUFFER_SIZE;
+    while buffer_size <= MAX_BUFFER_SIZE {
+        let mut buffer = vec![0u8; buffer_size];
+        // SAFETY: `buffer` is valid for `buffer_size` bytes and `format` is NUL-terminated.
+        let len = unsafe {
+            libc::strftime(
+                buffer.as_mut_ptr() as *mut libc::c_char,
+                buffer_size,
+                format.as_ptr(),
+                &tm,
+            )
+        };
+        // An empty result either means the buffer was too small or that the timestamp is empty
+        if len > 0 {
+            buffer.truncate(len);
+            return String::from_utf8_lossy(&buffer).into_owned();
+        }
+        buffer_size *= 2;
+    }
+    String::new()
+}
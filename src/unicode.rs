@@ -0,0 +1,97 @@
+//! # Unicode Module for RustCat
+//!
+//! This module classifies the Unicode code points that are invisible when displayed, for
+//! `--show-unicode` and `--check-bidi`, and finds the homoglyphs that `--show-unicode` shows.
+//!
+//! The bidirectional controls can reorder how source code is displayed without changing how it
+//! is compiled, as described by the "Trojan Source" attacks (CVE-2021-42574), so reviewers may
+//! approve code that does something else than it appears to. Zero-width characters, byte order
+//! marks and similar format characters can likewise hide differences between identifiers, as can
+//! Cyrillic and Greek letters that look like Latin ones, such as the Cyrillic `а` in `pаssword`.
+
+/// Checks if a character is a bidirectional embedding, override or isolate control, the
+/// characters used by the Trojan Source attacks.
+pub fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}')
+}
+
+/// Checks if a character is displayed as nothing at all, or only changes how the characters
+/// around it are displayed.
+///
+/// Control characters are left to `-v`, and whitespace to `--show-whitespace`.
+pub fn is_invisible(c: char) -> bool {
+    is_bidi_control(c)
+        || matches!(
+            c,
+            // Soft hyphen
+            '\u{ad}'
+            // Combining grapheme joiner
+            | '\u{34f}'
+            // Arabic letter mark
+            | '\u{61c}'
+            // Hangul fillers
+            | '\u{115f}' | '\u{1160}' | '\u{3164}' | '\u{ffa0}'
+            // Mongolian vowel separator
+            | '\u{180e}'
+            // Zero-width space, non-joiner and joiner, left-to-right and right-to-left marks
+            | '\u{200b}'..='\u{200f}'
+            // Word joiner and invisible operators
+            | '\u{2060}'..='\u{2064}'
+            // Byte order mark, or zero-width no-break space
+            | '\u{feff}'
+            // Tags, which can spell out hidden ASCII text
+            | '\u{e0000}'..='\u{e007f}'
+        )
+}
+
+/// Checks if a character is a Cyrillic or Greek letter that looks like a Latin letter.
+pub fn is_confusable(c: char) -> bool {
+    matches!(
+        c,
+        // Cyrillic lowercase letters, looking like a, e, o, p, c, y, x, s, i, j, d, h, q and w
+        '\u{430}' | '\u{435}' | '\u{43e}' | '\u{440}' | '\u{441}' | '\u{443}' | '\u{445}'
+            | '\u{455}' | '\u{456}' | '\u{458}' | '\u{501}' | '\u{4bb}' | '\u{51b}' | '\u{51d}'
+            // Cyrillic capital letters, looking like A, B, E, K, M, H, O, P, C, T, X, S, I and J
+            | '\u{410}' | '\u{412}' | '\u{415}' | '\u{41a}' | '\u{41c}' | '\u{41d}' | '\u{41e}'
+            | '\u{420}' | '\u{421}' | '\u{422}' | '\u{425}' | '\u{405}' | '\u{406}' | '\u{408}'
+            // Greek lowercase letters, looking like o and v
+            | '\u{3bf}' | '\u{3bd}'
+            // Greek capital letters, looking like A, B, E, Z, H, I, K, M, N, O, P, T, Y and X
+            | '\u{391}' | '\u{392}' | '\u{395}' | '\u{396}' | '\u{397}' | '\u{399}' | '\u{39a}'
+            | '\u{39c}' | '\u{39d}' | '\u{39f}' | '\u{3a1}' | '\u{3a4}' | '\u{3a5}' | '\u{3a7}'
+    )
+}
+
+/// Finds the homoglyphs of a text, the confusable letters of the words that also contain Latin
+/// letters, and returns their byte offsets.
+///
+/// Words written entirely in Cyrillic or Greek are left alone, so that prose in those scripts
+/// is displayed as it is.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(find_homoglyphs("let p\u{430}ss = \"\u{43e}\u{43a}\";"), vec![5]);
+/// ```
+pub fn find_homoglyphs(text: &str) -> Vec<usize> {
+    let mut homoglyphs = Vec::new();
+    let mut word = Vec::new();
+    let mut has_latin_letter = false;
+
+    // A trailing separator ends the last word like any other
+    for (index, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        if c.is_alphanumeric() || c == '_' {
+            has_latin_letter |= c.is_ascii_alphabetic();
+            if is_confusable(c) {
+                word.push(index);
+            }
+        } else {
+            if has_latin_letter {
+                homoglyphs.append(&mut word);
+            }
+            word.clear();
+            has_latin_letter = false;
+        }
+    }
+    homoglyphs
+}
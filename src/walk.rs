@@ -0,0 +1,160 @@
+//! # Walk Module for RustCat
+//!
+//! This module expands directory operands into the regular files they contain for `--recursive`.
+//! Directories are walked depth-first with the entries of each directory sorted by name, so the
+//! files are always concatenated in the same order.
+//!
+//! Like `git` and `ripgrep`, the files ignored by `.gitignore`, `.ignore` or the global git
+//! excludes are skipped, as are hidden files, unless `--no-ignore` or `--hidden` is given.
+//!
+//! The files can be filtered with `--include` and `--exclude` globs, matched against their path
+//! relative to the directory operand, and binary files can be skipped with `--skip-binary`.
+use crate::args::Cli;
+use crate::io::error_message;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::process;
+
+/// The number of bytes at the start of a file checked for NUL bytes to detect binary files.
+const BINARY_CHECK_LEN: u64 = 8192;
+
+/// Filters the files found in directories by their path.
+#[derive(Clone)]
+struct PathFilter {
+    /// Only the files matching one of these globs are kept, if there are any.
+    include: Option<GlobSet>,
+    /// The files and directories matching one of these globs are skipped.
+    exclude: GlobSet,
+}
+
+impl PathFilter {
+    /// Constructs a new `PathFilter` from the globs given on the command line.
+    fn new(include: &[String], exclude: &[String]) -> Result<Self, globset::Error> {
+        let build_glob_set = |globs: &[String]| {
+            let mut builder = GlobSetBuilder::new();
+            for glob in globs {
+                builder.add(Glob::new(glob)?);
+            }
+            builder.build()
+        };
+        let include = if include.is_empty() {
+            None
+        } else {
+            Some(build_glob_set(include)?)
+        };
+        Ok(PathFilter {
+            include,
+            exclude: build_glob_set(exclude)?,
+        })
+    }
+
+    /// Checks if a file or directory is excluded by its path relative to the directory operand.
+    ///
+    /// Both the relative path and the name alone are matched, so `target` excludes a directory
+    /// named `target` at any depth.
+    fn is_excluded(&self, relative_path: &Path) -> bool {
+        self.exclude.is_match(relative_path)
+            || relative_path
+                .file_name()
+                .is_some_and(|name| self.exclude.is_match(name))
+    }
+
+    /// Checks if a file is included by its path relative to the directory operand.
+    fn is_included(&self, relative_path: &Path) -> bool {
+        self.include.as_ref().is_none_or(|include| {
+            include.is_match(relative_path)
+                || relative_path
+                    .file_name()
+                    .is_some_and(|name| include.is_match(name))
+        })
+    }
+}
+
+/// Checks if a file looks binary, that is if a NUL byte occurs near its start.
+fn is_binary(path: &Path) -> io::Result<bool> {
+    let mut start = Vec::new();
+    File::open(path)?
+        .take(BINARY_CHECK_LEN)
+        .read_to_end(&mut start)?;
+    Ok(start.contains(&0))
+}
+
+/// Expands the directory operands into the regular files they contain, keeping other operands.
+///
+/// Errors are reported like errors reading the files, and exit the process.
+///
+/// # Examples
+///
+/// ```
+/// // With `--recursive --include '*.rs'`, `src` expands to `src/main.rs`, `src/args.rs`...
+/// let filenames = expand_operands(&cli.files, &cli);
+/// ```
+pub fn expand_operands(operands: &[String], cli: &Cli) -> Vec<String> {
+    let path_filter = match PathFilter::new(&cli.include, &cli.exclude) {
+        Ok(path_filter) => path_filter,
+        Err(e) => {
+            eprintln!("rustcat: invalid glob: {}", e);
+            process::exit(1);
+        }
+    };
+    let mut filenames = Vec::new();
+
+    for operand in operands {
+        if !Path::new(operand).is_dir() {
+            filenames.push(operand.clone());
+            continue;
+        }
+        if let Err(e) = walk_directory(operand, &path_filter, cli, &mut filenames) {
+            eprintln!("rustcat: {}: {}", operand, e);
+            process::exit(1);
+        }
+    }
+    filenames
+}
+
+/// Adds the regular files of a directory to `filenames`, in a stable sorted order.
+fn walk_directory(
+    directory: &str,
+    path_filter: &PathFilter,
+    cli: &Cli,
+    filenames: &mut Vec<String>,
+) -> Result<(), String> {
+    let root = Path::new(directory).to_path_buf();
+    let exclude_filter = path_filter.clone();
+    let walker = WalkBuilder::new(directory)
+        // Honour `.gitignore`, `.ignore` and the global git excludes unless told otherwise
+        .standard_filters(!cli.no_ignore)
+        .hidden(!cli.hidden)
+        // Ignore files also apply to directories that are not git repositories
+        .require_git(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        // Skip excluded directories without walking them
+        .filter_entry(move |entry| {
+            let relative_path = entry.path().strip_prefix(&root).unwrap_or(entry.path());
+            relative_path.as_os_str().is_empty() || !exclude_filter.is_excluded(relative_path)
+        })
+        .build();
+
+    for entry in walker {
+        let entry = entry.map_err(|e| e.to_string())?;
+        if !entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_file())
+        {
+            continue;
+        }
+        let path = entry.path();
+        let relative_path = path.strip_prefix(directory).unwrap_or(path);
+        if !path_filter.is_included(relative_path) {
+            continue;
+        }
+        if cli.skip_binary && is_binary(path).map_err(|e| error_message(&e))? {
+            continue;
+        }
+        filenames.push(path.to_string_lossy().into_owned());
+    }
+    Ok(())
+}
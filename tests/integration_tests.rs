@@ -15,10 +15,15 @@
 //! These tests use a combination of simulated command-line invocations and file operations to
 //! validate the correctness and reliability of RustCat under different conditions and inputs.
 use assert_cmd::prelude::*;
+use assert_fs::prelude::*;
 use predicates::prelude::*;
 use std::env;
 use std::fs::{self};
-use std::process::Command;
+use std::io::{self, Read, Write};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 /// Compares the output of RustCat and the traditional Unix `cat` command.
 fn compare_rustcat_and_cat(test_args: &Vec<&str>) {
@@ -79,6 +84,55 @@ fn compare_rustcat_and_cat_for_linux(rustcat_args: &Vec<&str>, cat_args: &Vec<&s
         );
     }
 }
+/// Compares the output of a RustCat search with the numbered RustCat output filtered by the search term.
+///
+/// Every printed line must carry exactly the number it has without searching.
+fn compare_search_with_numbering(test_args: &Vec<&str>, term: &str) {
+    let test_files = fs::read_dir("test_data").unwrap();
+
+    for file in test_files {
+        let file = file.unwrap();
+        let file_name = file.file_name();
+        let file_name = file_name.to_str().unwrap();
+        println!("Testing file: {}", file_name);
+        let file_path = format!("test_data/{}", file_name);
+
+        let mut numbered_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+        let mut search_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+        let numbered_output = numbered_cmd
+            .args(test_args)
+            .arg(file_path.clone())
+            .unwrap()
+            .stdout;
+        let search_output = search_cmd
+            .args(test_args)
+            .args(["-f", term])
+            .arg(file_path.clone())
+            .unwrap()
+            .stdout;
+
+        // Keep the numbered lines whose content contains the term and highlight it
+        let highlighted_term = format!("\x1b[33m{}\x1b[0m", term);
+        let expected: String = String::from_utf8_lossy(&numbered_output)
+            .split_inclusive('\n')
+            .filter_map(|line| {
+                let (number, content) = match line.get(..7) {
+                    Some(number) if number.ends_with('\t') => line.split_at(7),
+                    _ => ("", line),
+                };
+                content
+                    .contains(term)
+                    .then(|| format!("{}{}", number, content.replace(term, &highlighted_term)))
+            })
+            .collect();
+        assert_eq!(
+            String::from_utf8_lossy(&search_output),
+            expected,
+            "Mismatch in file {:?}",
+            file_path
+        );
+    }
+}
 /// The rest of the tests are testing the basic fuctionality of the program.
 #[test]
 fn test_help() {
@@ -431,3 +485,1811 @@ fn test_e_s() {
 
     compare_rustcat_and_cat(&test_args);
 }
+
+/// Removes ANSI escape sequences from the output, asserting that every sequence is well-formed.
+fn strip_ansi_escapes(output: &str) -> String {
+    let mut stripped = String::new();
+    let mut chars = output.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            assert_eq!(chars.next(), Some('['), "Malformed escape in {:?}", output);
+            // Parameters are digits and semicolons, terminated by `m`
+            loop {
+                match chars.next() {
+                    Some('m') => break,
+                    Some(p) if p.is_ascii_digit() || p == ';' => continue,
+                    other => panic!("Malformed escape ending in {:?} in {:?}", other, output),
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+#[test]
+fn test_search_highlights_original_text() {
+    let file = assert_fs::NamedTempFile::new("sample.txt").unwrap();
+    file.write_str("main\tm\nnothing\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-A", "-n", "-f", "m"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("     1\t\x1b[33mm\x1b[0main^I\x1b[33mm\x1b[0m$\n");
+}
+
+#[test]
+fn test_search_does_not_match_inside_notation() {
+    let file = assert_fs::NamedTempFile::new("sample.txt").unwrap();
+    file.write_str("a\tb\n").unwrap();
+
+    // `I` only appears in the `^I` notation and `$` only in the line end marker
+    for term in ["I", "$", "1"] {
+        let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+        rustcat_cmd
+            .args(["-A", "-n", "-f", term])
+            .arg(file.path())
+            .assert()
+            .success()
+            .stdout("");
+    }
+}
+
+#[test]
+fn test_search_with_syntax_highlighting_is_well_formed() {
+    let file = assert_fs::NamedTempFile::new("sample.rs").unwrap();
+    file.write_str("fn main() {\n\tlet m = 38;\n}\n").unwrap();
+
+    for term in ["m", "38", ";", "[", "2"] {
+        let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+        let output = rustcat_cmd
+            .args(["-x", "-n", "-f", term])
+            .arg(file.path())
+            .unwrap()
+            .stdout;
+        let output = String::from_utf8_lossy(&output);
+        let expected: String = ["     1\tfn main() {\n", "     2\t\tlet m = 38;\n"]
+            .iter()
+            .filter(|line| line[7..].contains(term))
+            .copied()
+            .collect();
+        assert_eq!(
+            strip_ansi_escapes(&output),
+            expected,
+            "Search term {:?}",
+            term
+        );
+    }
+}
+
+#[test]
+fn test_search_line_numbers() {
+    compare_search_with_numbering(&vec!["-n"], "e");
+    compare_search_with_numbering(&vec!["-n", "-s"], "e");
+}
+
+#[test]
+fn test_search_non_blank_line_numbers() {
+    compare_search_with_numbering(&vec!["-b"], "e");
+    compare_search_with_numbering(&vec!["-b", "-s"], "e");
+}
+
+#[test]
+fn test_search_non_blank_line_numbers_skips_blank_lines() {
+    let file = assert_fs::NamedTempFile::new("sample.txt").unwrap();
+    file.write_str("one\n\n\n\ntwo\nthree\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-b", "-s", "-f", "t"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("     2\t\x1b[33mt\x1b[0mwo\n     3\t\x1b[33mt\x1b[0mhree\n");
+}
+
+#[test]
+fn test_search_whole_word() {
+    let file = assert_fs::NamedTempFile::new("sample.rs").unwrap();
+    file.write_str("let id = valid(identity, id_2);\nfn id() {}\nvalid\n")
+        .unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-n", "-w", "-f", "id"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout(
+            "     1\tlet \x1b[33mid\x1b[0m = valid(identity, id_2);\n     2\tfn \x1b[33mid\x1b[0m() {}\n",
+        );
+}
+
+#[test]
+fn test_search_only_matching() {
+    let file = assert_fs::NamedTempFile::new("sample.txt").unwrap();
+    file.write_str("one two one\nthree\n\tone\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-o", "-f", "one"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("one\none\none\n");
+}
+
+#[test]
+fn test_search_only_matching_with_line_numbers_and_columns() {
+    let file = assert_fs::NamedTempFile::new("sample.txt").unwrap();
+    file.write_str("one two one\nthree\n\tone\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-n", "-o", "--column", "-f", "one"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("     1\t1:one\n     1\t9:one\n     3\t2:one\n");
+}
+
+#[test]
+fn test_search_options_require_search_term() {
+    for arg in ["-w", "-o"] {
+        let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+        rustcat_cmd
+            .arg(arg)
+            .arg("test_data/python_file.py")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("--search <SEARCH>"));
+    }
+}
+
+#[test]
+fn test_search_with_filename() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("one.txt").write_str("one\ntwo\n").unwrap();
+    dir.child("two.txt").write_str("three\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .current_dir(dir.path())
+        .args(["-H", "-n", "-f", "t", "one.txt", "two.txt"])
+        .assert()
+        .success()
+        .stdout(concat!(
+            "\x1b[35mone.txt\x1b[0m:     2\t\x1b[33mt\x1b[0mwo\n",
+            "\x1b[35mtwo.txt\x1b[0m:     3\t\x1b[33mt\x1b[0mhree\n",
+        ))
+        .stderr("rustcat: 2 matching lines in 2 of 2 files\n");
+}
+
+#[test]
+fn test_search_files_with_matches() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("one.txt").write_str("one\ntwo\n").unwrap();
+    dir.child("two.txt").write_str("three\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .current_dir(dir.path())
+        .args(["-l", "-f", "o", "one.txt", "two.txt"])
+        .assert()
+        .success()
+        .stdout("one.txt\n")
+        .stderr("rustcat: 2 matching lines in 1 of 2 files\n");
+}
+
+#[test]
+fn test_search_files_without_match() {
+    let dir = assert_fs::TempDir::new().unwrap();
+    dir.child("one.txt").write_str("one\ntwo\n").unwrap();
+    dir.child("two.txt").write_str("three\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .current_dir(dir.path())
+        .args(["-L", "-f", "o", "one.txt", "two.txt"])
+        .assert()
+        .success()
+        .stdout("two.txt\n");
+}
+
+#[test]
+fn test_search_single_file_has_no_summary() {
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-f", "print", "test_data/python_file.py"])
+        .assert()
+        .success()
+        .stderr("");
+}
+
+#[test]
+fn test_search_fuzzy() {
+    let file = assert_fs::NamedTempFile::new("sample.yaml").unwrap();
+    file.write_str("server:\n  config: on\n  count: 1 of a much, much longer gap\n")
+        .unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-n", "--fuzzy", "-f", "CFG"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("     2\t  \x1b[33mc\x1b[0mon\x1b[33mf\x1b[0mi\x1b[33mg\x1b[0m: on\n");
+}
+
+#[test]
+fn test_search_fuzzy_merges_consecutive_characters() {
+    let file = assert_fs::NamedTempFile::new("sample.txt").unwrap();
+    file.write_str("a config\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--fuzzy", "-f", "conf"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("a \x1b[33mconf\x1b[0mig\n");
+}
+
+#[test]
+fn test_search_fuzzy_threshold() {
+    let file = assert_fs::NamedTempFile::new("sample.yaml").unwrap();
+    file.write_str("server:\n  config: on\n  count: 1 of a much, much longer gap\n")
+        .unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    let output = rustcat_cmd
+        .args(["-n", "--fuzzy", "--fuzzy-threshold", "0", "-f", "cfg"])
+        .arg(file.path())
+        .unwrap()
+        .stdout;
+    let output = String::from_utf8_lossy(&output);
+    assert!(output.contains("     2\t"));
+    assert!(output.contains("     3\t"));
+    assert!(!output.contains("     1\t"));
+}
+
+#[test]
+fn test_search_fuzzy_conflicts_with_whole_word() {
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--fuzzy", "-w", "-f", "cfg", "test_data/python_file.py"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn test_lines_ranges() {
+    let file = assert_fs::NamedTempFile::new("sample.txt").unwrap();
+    let content: String = (1..=30).map(|i| format!("line {}\n", i)).collect();
+    file.write_str(&content).unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-n", "--lines", "3:4,-2:,10"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout(concat!(
+            "     3\tline 3\n",
+            "     4\tline 4\n",
+            "    10\tline 10\n",
+            "    29\tline 29\n",
+            "    30\tline 30\n",
+        ));
+}
+
+#[test]
+fn test_lines_open_ranges() {
+    let file = assert_fs::NamedTempFile::new("sample.txt").unwrap();
+    file.write_str("one\ntwo\nthree\nfour\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--lines=:2"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("one\ntwo\n");
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--lines", "3:"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("three\nfour\n");
+}
+
+#[test]
+fn test_lines_with_search() {
+    let file = assert_fs::NamedTempFile::new("sample.txt").unwrap();
+    file.write_str("one\ntwo\nthree\nfour\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-n", "--lines", "2:3", "-f", "o"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("     2\ttw\x1b[33mo\x1b[0m\n");
+}
+
+#[test]
+fn test_lines_keep_syntax_context() {
+    let file = assert_fs::NamedTempFile::new("sample.rs").unwrap();
+    file.write_str("/* a comment\nstill a comment\n*/\nfn main() {}\n")
+        .unwrap();
+
+    let mut full_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    let full_output = full_cmd.arg("-x").arg(file.path()).unwrap().stdout;
+    let full_output = String::from_utf8_lossy(&full_output);
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-x", "--lines", "2"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout(
+            full_output
+                .split_inclusive('\n')
+                .nth(1)
+                .unwrap()
+                .to_string(),
+        );
+}
+
+#[test]
+fn test_lines_invalid_range() {
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--lines", "0:2", "test_data/python_file.py"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("positions start at 1"));
+}
+
+#[test]
+fn test_bytes_range() {
+    let file = assert_fs::NamedTempFile::new("sample.txt").unwrap();
+    file.write_str("hello\nworld\x01\nlast line\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-v", "--bytes", "3:14"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("llo\nworld^A\nl");
+}
+
+#[test]
+fn test_bytes_range_of_binary_data() {
+    let file = assert_fs::NamedTempFile::new("sample.bin").unwrap();
+    file.write_binary(b"ab\xff\xfecd\n\xe4\xb8").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-v", "--bytes", "1:"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("abM-^?M-~cd\nM-dM-8");
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-v", "--nonprinting-style=hex", "--bytes", "1:"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("ab\\xff\\xfecd\n\\xe4\\xb8");
+
+    // Without -v, the bytes are written back unchanged
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--offset", "--bytes", "3:"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout(&b"       2\t\xff\xfecd\n       7\t\xe4\xb8"[..]);
+}
+
+#[test]
+fn test_private_use_characters_are_kept() {
+    let file = assert_fs::NamedTempFile::new("sample.txt").unwrap();
+    file.write_str("a\u{10ff80}b\nc\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .arg("-v")
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("a\u{10ff80}b\nc\n");
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .arg("--offset")
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("       0\ta\u{10ff80}b\n       7\tc\n");
+
+    // In a window with raw bytes, the character is kept byte by byte
+    file.write_binary(b"\xff\xf4\x8f\xbe\x80\n").unwrap();
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--bytes", "1:"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout(&b"\xff\xf4\x8f\xbe\x80\n"[..]);
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-v", "--bytes", "1:"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("M-^?M-tM-^OM->M-^@\n");
+}
+
+#[test]
+fn test_bytes_range_from_end() {
+    let file = assert_fs::NamedTempFile::new("sample.txt").unwrap();
+    file.write_str("hello\nworld\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--bytes=-3:"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("ld\n");
+}
+
+#[test]
+fn test_bytes_range_of_unseekable_input() {
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--offset", "--bytes", "7:", "/dev/stdin"])
+        .write_stdin("hello\nworld\nlast line\n")
+        .assert()
+        .success()
+        .stdout("       6\tworld\n      12\tlast line\n");
+}
+
+#[test]
+fn test_byte_offset() {
+    let file = assert_fs::NamedTempFile::new("sample.txt").unwrap();
+    file.write_str("hello\n\n\n\nworld\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-s", "--offset"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("       0\thello\n       6\t\n       9\tworld\n");
+}
+
+#[test]
+fn test_head() {
+    let file = assert_fs::NamedTempFile::new("sample.txt").unwrap();
+    file.write_str("one\ntwo\nthree\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-n", "--head", "2"])
+        .arg(file.path())
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("     1\tone\n     2\ttwo\n     4\tone\n     5\ttwo\n");
+}
+
+#[test]
+fn test_tail() {
+    let file = assert_fs::NamedTempFile::new("sample.txt").unwrap();
+    let content: String = (1..=10000).map(|i| format!("line {}\n", i)).collect();
+    file.write_str(&content).unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-n", "--tail", "2"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("  9999\tline 9999\n 10000\tline 10000\n");
+}
+
+#[test]
+fn test_tail_without_trailing_newline() {
+    let file = assert_fs::NamedTempFile::new("sample.txt").unwrap();
+    file.write_str("one\ntwo\nthree").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--tail", "2"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("two\nthree");
+}
+
+#[test]
+fn test_tail_of_unseekable_input() {
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-n", "--tail", "2", "/dev/stdin"])
+        .write_stdin("one\ntwo\nthree\n")
+        .assert()
+        .success()
+        .stdout("     2\ttwo\n     3\tthree\n");
+}
+
+#[test]
+fn test_tail_matches_lines() {
+    let test_files = fs::read_dir("test_data").unwrap();
+
+    for file in test_files {
+        let file_path = file.unwrap().path();
+        for args in [vec!["-n"], vec!["-b", "-s"], vec!["-v", "-E"]] {
+            let mut tail_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+            let mut lines_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+            let tail_output = tail_cmd
+                .args(&args)
+                .args(["--tail", "5"])
+                .arg(&file_path)
+                .unwrap()
+                .stdout;
+            let lines_output = lines_cmd
+                .args(&args)
+                .args(["--lines=-5:"])
+                .arg(&file_path)
+                .unwrap()
+                .stdout;
+            assert_eq!(
+                String::from_utf8_lossy(&tail_output),
+                String::from_utf8_lossy(&lines_output),
+                "Mismatch in file {:?}",
+                file_path
+            );
+        }
+    }
+}
+
+/// How long to wait for rustcat to display something before failing a test.
+const OUTPUT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A rustcat process run with `--follow` on a file, whose output is collected as it arrives.
+struct Follower {
+    /// The rustcat process.
+    child: Child,
+    /// The output written to stdout so far.
+    stdout: Arc<Mutex<Vec<u8>>>,
+    /// The output written to stderr so far.
+    stderr: Arc<Mutex<Vec<u8>>>,
+    /// The threads collecting the output.
+    readers: Vec<thread::JoinHandle<()>>,
+}
+
+impl Follower {
+    /// Runs rustcat with `--follow` and the given arguments on a file.
+    fn new(args: &[&str], file: &assert_fs::fixture::ChildPath) -> Self {
+        let mut child = Command::cargo_bin("rustcat")
+            .unwrap()
+            .args(args)
+            .arg("--follow")
+            .arg(file.path())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdout = Arc::new(Mutex::new(Vec::new()));
+        let stderr = Arc::new(Mutex::new(Vec::new()));
+        let readers = vec![
+            collect_output(child.stdout.take().unwrap(), Arc::clone(&stdout)),
+            collect_output(child.stderr.take().unwrap(), Arc::clone(&stderr)),
+        ];
+        Follower {
+            child,
+            stdout,
+            stderr,
+            readers,
+        }
+    }
+
+    /// Waits until stdout contains `expected`.
+    fn wait_for_stdout(&self, expected: &str) {
+        wait_for_output(&self.stdout, expected);
+    }
+
+    /// Waits until stderr contains `expected`.
+    fn wait_for_stderr(&self, expected: &str) {
+        wait_for_output(&self.stderr, expected);
+    }
+
+    /// Interrupts rustcat with `SIGINT` and returns its exit status, stdout and stderr.
+    fn interrupt(mut self) -> (ExitStatus, String, String) {
+        let status = Command::new("kill")
+            .args(["-INT", &self.child.id().to_string()])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let status = self.child.wait().unwrap();
+        for reader in self.readers {
+            reader.join().unwrap();
+        }
+        let stdout = String::from_utf8_lossy(&self.stdout.lock().unwrap()).into_owned();
+        let stderr = String::from_utf8_lossy(&self.stderr.lock().unwrap()).into_owned();
+        (status, stdout, stderr)
+    }
+}
+
+/// Appends everything read from `pipe` to `output` on another thread.
+fn collect_output(
+    mut pipe: impl Read + Send + 'static,
+    output: Arc<Mutex<Vec<u8>>>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut buffer = [0; 4096];
+        while let Ok(len @ 1..) = pipe.read(&mut buffer) {
+            output.lock().unwrap().extend_from_slice(&buffer[..len]);
+        }
+    })
+}
+
+/// Waits until `output` contains `expected`, failing after `OUTPUT_TIMEOUT`.
+fn wait_for_output(output: &Mutex<Vec<u8>>, expected: &str) {
+    let start = Instant::now();
+    loop {
+        let text = String::from_utf8_lossy(&output.lock().unwrap()).into_owned();
+        if text.contains(expected) {
+            return;
+        }
+        assert!(
+            start.elapsed() < OUTPUT_TIMEOUT,
+            "timed out waiting for {:?}, got {:?}",
+            expected,
+            text
+        );
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn test_follow_numbers_appended_lines() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let file = temp.child("app.log");
+    file.write_str("first\nsecond\npart").unwrap();
+
+    let follower = Follower::new(&["-n"], &file);
+    follower.wait_for_stdout("     2\tsecond\n");
+    let mut log = fs::OpenOptions::new()
+        .append(true)
+        .open(file.path())
+        .unwrap();
+    log.write_all(b"ial\nthird\n").unwrap();
+    follower.wait_for_stdout("     4\tthird\n");
+    let (status, stdout, _) = follower.interrupt();
+    // Interrupting the follow is a clean exit
+    assert!(status.success());
+    assert_eq!(
+        stdout,
+        "     1\tfirst\n     2\tsecond\n     3\tpartial\n     4\tthird\n"
+    );
+}
+
+#[test]
+fn test_follow_detects_truncation_and_rotation() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let file = temp.child("app.log");
+    file.write_str("old\n").unwrap();
+
+    let follower = Follower::new(&[], &file);
+    follower.wait_for_stdout("old\n");
+    fs::write(file.path(), "").unwrap();
+    follower.wait_for_stderr("app.log: file truncated");
+    fs::write(file.path(), "truncated\n").unwrap();
+    follower.wait_for_stdout("truncated\n");
+    fs::rename(file.path(), temp.child("app.log.1").path()).unwrap();
+    fs::write(file.path(), "rotated\n").unwrap();
+    follower.wait_for_stdout("rotated\n");
+    let (status, stdout, stderr) = follower.interrupt();
+    assert!(status.success());
+    assert_eq!(stdout, "old\ntruncated\nrotated\n");
+    assert!(stderr.contains("app.log: file replaced"));
+}
+
+#[test]
+fn test_timestamp_prefixes_lines_before_line_numbers() {
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args([
+            "-n",
+            "--timestamp=[%H:%M]",
+            "--timestamp-relative",
+            "/dev/stdin",
+        ])
+        .write_stdin("one\ntwo\n")
+        .assert()
+        .success()
+        .stdout("[00:00]\t     1\tone\n[00:00]\t     2\ttwo\n");
+}
+
+#[test]
+fn test_timestamp_default_and_subsecond_formats() {
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    let output = rustcat_cmd
+        .args(["--timestamp", "/dev/stdin"])
+        .write_stdin("line\n")
+        .unwrap()
+        .stdout;
+    // The default format is the one of `ts`, such as `Oct 19 14:03:07`
+    let default_format = mask_timestamp(&String::from_utf8_lossy(&output));
+    assert_eq!(default_format, "Aaa 00 00:00:00\tline\n");
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    let output = rustcat_cmd
+        .args(["--timestamp=%.s|%%", "/dev/stdin"])
+        .write_stdin("line\n")
+        .unwrap()
+        .stdout;
+    let subsecond_format = mask_timestamp(&String::from_utf8_lossy(&output));
+    assert_eq!(subsecond_format, "0000000000.000000|%\tline\n");
+}
+
+/// Replaces the digits and letters before the first tab with `0` and `a`, keeping the case.
+fn mask_timestamp(line: &str) -> String {
+    let (timestamp, rest) = line.split_once('\t').unwrap();
+    let timestamp: String = timestamp
+        .chars()
+        .map(|c| match c {
+            '0'..='9' => '0',
+            'A'..='Z' => 'A',
+            'a'..='z' => 'a',
+            c => c,
+        })
+        .collect();
+    format!("{}\t{}", timestamp, rest)
+}
+
+#[test]
+fn test_timestamp_streams_slow_input() {
+    let mut child = Command::cargo_bin("rustcat")
+        .unwrap()
+        .args(["--timestamp=%.s", "--timestamp-relative", "/dev/stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stdout = Arc::new(Mutex::new(Vec::new()));
+    let reader = collect_output(child.stdout.take().unwrap(), Arc::clone(&stdout));
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"early\n").unwrap();
+    // Each line is displayed as soon as it is read, not once the input ended
+    wait_for_output(&stdout, "\tearly\n");
+    thread::sleep(Duration::from_millis(300));
+    stdin.write_all(b"late\n").unwrap();
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+    reader.join().unwrap();
+
+    let stdout = String::from_utf8_lossy(&stdout.lock().unwrap()).into_owned();
+    let stamps: Vec<f64> = stdout
+        .lines()
+        .zip(["early", "late"])
+        .map(|(line, text)| {
+            let (stamp, rest) = line.split_once('\t').unwrap();
+            assert_eq!(rest, text);
+            // Seconds with six decimals of microseconds
+            assert!(stamp.len() > 7 && stamp.as_bytes()[stamp.len() - 7] == b'.');
+            stamp.parse().unwrap()
+        })
+        .collect();
+    assert_eq!(stamps.len(), 2);
+    // The late line is stamped with the time it was read, after the pause
+    assert!(stamps[1] - stamps[0] >= 0.3);
+}
+
+/// Compresses `content` in every format supported by `--decompress`, with matching file names.
+fn compress_in_every_format(content: &[u8]) -> Vec<(&'static str, Vec<u8>)> {
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzip.write_all(content).unwrap();
+    let mut bzip2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+    bzip2.write_all(content).unwrap();
+    let mut xz = xz2::write::XzEncoder::new(Vec::new(), 6);
+    xz.write_all(content).unwrap();
+    vec![
+        ("app.log.gz", gzip.finish().unwrap()),
+        ("app.log.zst", zstd::encode_all(content, 0).unwrap()),
+        ("app.log.bz2", bzip2.finish().unwrap()),
+        ("app.log.xz", xz.finish().unwrap()),
+    ]
+}
+
+#[test]
+fn test_decompress_every_format() {
+    let temp = assert_fs::TempDir::new().unwrap();
+
+    for (name, compressed) in compress_in_every_format(b"first\n\n\n\tsecond\n") {
+        let file = temp.child(name);
+        file.write_binary(&compressed).unwrap();
+        let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+        rustcat_cmd
+            .args(["-z", "-n", "-s", "-T"])
+            .arg(file.path())
+            .assert()
+            .success()
+            .stdout("     1\tfirst\n     2\t\n     3\t^Isecond\n");
+    }
+}
+
+#[test]
+fn test_decompress_detects_format_from_content() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    // The extension does not matter, and plain files are read as they are
+    let compressed = temp.child("rotated.log");
+    compressed
+        .write_binary(&zstd::encode_all(&b"old\n"[..], 0).unwrap())
+        .unwrap();
+    let plain = temp.child("current.log.gz");
+    plain.write_str("new\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--decompress", "-n"])
+        .arg(compressed.path())
+        .arg(plain.path())
+        .assert()
+        .success()
+        .stdout("     1\told\n     2\tnew\n");
+}
+
+#[test]
+fn test_decompress_concatenated_streams_and_selection() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let file = temp.child("app.log.gz");
+    // Appending to a compressed log adds another stream
+    let mut compressed = compress_in_every_format(b"one\ntwo\n").remove(0).1;
+    compressed.extend(compress_in_every_format(b"three\nfour\n").remove(0).1);
+    file.write_binary(&compressed).unwrap();
+
+    for (args, expected) in [
+        (vec!["--tail", "2", "-n"], "     3\tthree\n     4\tfour\n"),
+        (vec!["--head", "1"], "one\n"),
+        (
+            vec!["--bytes=5:14", "--offset"],
+            "       4\ttwo\n       8\tthree\n",
+        ),
+        (vec!["-f", "o", "-o"], "o\no\no\n"),
+    ] {
+        let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+        rustcat_cmd
+            .arg("-z")
+            .args(&args)
+            .arg(file.path())
+            .assert()
+            .success()
+            .stdout(expected);
+    }
+}
+
+#[test]
+fn test_decompress_piped_input() {
+    let compressed = compress_in_every_format(b"piped\n").remove(1).1;
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-z", "-E", "/dev/stdin"])
+        .write_stdin(compressed)
+        .assert()
+        .success()
+        .stdout("piped$\n");
+}
+
+/// Archives `members` in every format supported for archive members, with matching file names.
+fn archive_in_every_format(members: &[(&str, &[u8])]) -> Vec<(&'static str, Vec<u8>)> {
+    let gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    let mut tar_builder = tar::Builder::new(gzip);
+    let mut zip_writer = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+
+    for (name, content) in members {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        tar_builder
+            .append_data(&mut header, name, *content)
+            .unwrap();
+        zip_writer
+            .start_file(*name, zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip_writer.write_all(content).unwrap();
+    }
+    vec![
+        (
+            "bundle.tar.gz",
+            tar_builder.into_inner().unwrap().finish().unwrap(),
+        ),
+        ("release.zip", zip_writer.finish().unwrap().into_inner()),
+    ]
+}
+
+#[test]
+fn test_archive_members_are_displayed() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    for (name, archive) in archive_in_every_format(&[
+        ("src/main.rs", b"fn main() {}\n"),
+        ("config.yaml", b"a: 1\n\n\nb: 2\n"),
+    ]) {
+        temp.child(name).write_binary(&archive).unwrap();
+    }
+
+    for archive in ["bundle.tar.gz", "release.zip"] {
+        let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+        rustcat_cmd
+            .current_dir(temp.path())
+            .args(["-n", "-s"])
+            .arg(format!("{}::config.yaml", archive))
+            .arg(format!("{}::src/main.rs", archive))
+            .assert()
+            .success()
+            .stdout("     1\ta: 1\n     2\t\n     3\tb: 2\n     4\tfn main() {}\n");
+    }
+}
+
+#[test]
+fn test_archive_members_use_their_own_syntax() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    for (name, archive) in archive_in_every_format(&[("config.yaml", b"a: 1\n")]) {
+        temp.child(name).write_binary(&archive).unwrap();
+    }
+    let plain = temp.child("config.yaml");
+    plain.write_str("a: 1\n").unwrap();
+
+    let mut plain_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    let plain_output = plain_cmd.arg("-x").arg(plain.path()).unwrap().stdout;
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .current_dir(temp.path())
+        .args(["-x", "release.zip::config.yaml"])
+        .assert()
+        .success()
+        .stdout(plain_output);
+}
+
+#[test]
+fn test_list_archive() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    for (name, archive) in archive_in_every_format(&[("src/main.rs", b""), ("config.yaml", b"")]) {
+        temp.child(name).write_binary(&archive).unwrap();
+    }
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .current_dir(temp.path())
+        .args(["--list-archive", "bundle.tar.gz", "release.zip"])
+        .assert()
+        .success()
+        .stdout("src/main.rs\nconfig.yaml\nsrc/main.rs\nconfig.yaml\n");
+}
+
+#[test]
+fn test_missing_archive_member() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    for (name, archive) in archive_in_every_format(&[("config.yaml", b"")]) {
+        temp.child(name).write_binary(&archive).unwrap();
+    }
+
+    for archive in ["bundle.tar.gz", "release.zip"] {
+        let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+        rustcat_cmd
+            .current_dir(temp.path())
+            .arg(format!("{}::missing.yaml", archive))
+            .assert()
+            .failure()
+            .stderr(format!(
+                "rustcat: {}::missing.yaml: No such member in archive\n",
+                archive
+            ));
+    }
+}
+
+#[test]
+fn test_decompress_archive_members() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let compressed = compress_in_every_format(b"first\n\n\nsecond\n");
+    let members: Vec<(&str, &[u8])> = compressed
+        .iter()
+        .map(|(name, content)| (*name, content.as_slice()))
+        .collect();
+    for (name, archive) in archive_in_every_format(&members) {
+        temp.child(name).write_binary(&archive).unwrap();
+    }
+
+    for archive in ["bundle.tar.gz", "release.zip"] {
+        for (member, _) in &members {
+            let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+            rustcat_cmd
+                .current_dir(temp.path())
+                .args(["-z", "-n", "-s"])
+                .arg(format!("{}::{}", archive, member))
+                .assert()
+                .success()
+                .stdout("     1\tfirst\n     2\t\n     3\tsecond\n");
+        }
+    }
+}
+
+/// Builds a RustCat command run in `dir`, isolated from the git configuration of the user.
+///
+/// Recursive walks honour the global git excludes, so an entry such as `target` in the user's
+/// `~/.config/git/ignore` would otherwise change which files are displayed.
+fn rustcat_in_tree(dir: &std::path::Path) -> assert_cmd::Command {
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .current_dir(dir)
+        .env("XDG_CONFIG_HOME", dir)
+        .env("HOME", dir);
+    rustcat_cmd
+}
+
+#[test]
+fn test_recursive_walks_in_sorted_order() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("src/main.rs").write_str("main\n").unwrap();
+    temp.child("src/b/lib.rs").write_str("lib\n").unwrap();
+    temp.child("src/a.txt").write_str("text\n").unwrap();
+    temp.child("src/data.bin")
+        .write_binary(b"\x00\x01\n")
+        .unwrap();
+    temp.child("target/debug.rs").write_str("built\n").unwrap();
+    temp.child("README").write_str("readme\n").unwrap();
+    let rule = |junction: char| format!("{}{}{}\n", "─".repeat(7), junction, "─".repeat(72));
+    // Each file is displayed in its own header box
+    let boxed = |name: &str, size: &str, language: &str, content: &str| {
+        format!(
+            "{}       │ File: {}\n       │ Size: {}\n       │ Language: {}\n{}{}{}",
+            rule('┬'),
+            name,
+            size,
+            language,
+            rule('┼'),
+            content,
+            rule('┴'),
+        )
+    };
+
+    rustcat_in_tree(temp.path())
+        .args(["-r", "--header", "--decorations=always", "README", "src"])
+        .assert()
+        .success()
+        .stdout(
+            [
+                boxed("README", "7 B", "Plain Text", "readme\n"),
+                boxed("src/a.txt", "5 B", "Plain Text", "text\n"),
+                boxed("src/b/lib.rs", "4 B", "Rust", "lib\n"),
+                boxed("src/data.bin", "3 B", "Plain Text", "\x00\x01\n"),
+                boxed("src/main.rs", "5 B", "Rust", "main\n"),
+            ]
+            .concat(),
+        );
+}
+
+#[test]
+fn test_recursive_include_exclude_and_skip_binary() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("src/main.rs").write_str("main\n").unwrap();
+    temp.child("src/b/lib.rs").write_str("lib\n").unwrap();
+    temp.child("src/a.txt").write_str("text\n").unwrap();
+    temp.child("src/data.bin")
+        .write_binary(b"\x00\x01\n")
+        .unwrap();
+    temp.child("target/debug.rs").write_str("built\n").unwrap();
+    temp.child("README").write_str("readme\n").unwrap();
+
+    for (args, expected) in [
+        (vec!["--include", "*.rs"], "lib\nmain\nbuilt\n"),
+        (
+            vec!["--include", "*.rs", "--exclude", "target"],
+            "lib\nmain\n",
+        ),
+        (
+            vec!["--exclude", "src/b/*", "--exclude", "*.rs"],
+            "readme\ntext\n\x00\x01\n",
+        ),
+        (
+            vec!["--skip-binary", "--exclude", "target"],
+            "readme\ntext\nlib\nmain\n",
+        ),
+    ] {
+        rustcat_in_tree(temp.path())
+            .arg("--recursive")
+            .args(&args)
+            .arg(".")
+            .assert()
+            .success()
+            .stdout(expected);
+    }
+}
+
+#[test]
+fn test_recursive_respects_ignore_files() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("src/main.rs").write_str("main\n").unwrap();
+    temp.child("src/b/lib.rs").write_str("lib\n").unwrap();
+    temp.child("src/a.txt").write_str("text\n").unwrap();
+    temp.child("src/data.bin")
+        .write_binary(b"\x00\x01\n")
+        .unwrap();
+    temp.child("target/debug.rs").write_str("built\n").unwrap();
+    temp.child("README").write_str("readme\n").unwrap();
+    temp.child(".gitignore")
+        .write_str("target/\n*.txt\n")
+        .unwrap();
+    temp.child("src/.ignore").write_str("b/\n").unwrap();
+    temp.child(".hidden/secret.rs")
+        .write_str("secret\n")
+        .unwrap();
+    let config = assert_fs::TempDir::new().unwrap();
+    config.child("git/ignore").write_str("README\n").unwrap();
+
+    for (args, expected) in [
+        (vec![], "\x00\x01\nmain\n"),
+        (
+            vec!["--hidden"],
+            "target/\n*.txt\nsecret\nb/\n\x00\x01\nmain\n",
+        ),
+        (
+            vec!["--no-ignore"],
+            "readme\ntext\nlib\n\x00\x01\nmain\nbuilt\n",
+        ),
+    ] {
+        let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+        rustcat_cmd
+            .current_dir(temp.path())
+            // Global git excludes are read from the git configuration directory
+            .env("XDG_CONFIG_HOME", config.path())
+            .env("HOME", config.path())
+            .arg("-r")
+            .args(&args)
+            .arg(".")
+            .assert()
+            .success()
+            .stdout(expected);
+    }
+}
+
+#[test]
+fn test_files_from_list() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("with space.txt").write_str("spaced\n").unwrap();
+    temp.child("plain.txt").write_str("plain\n").unwrap();
+    temp.child("list.txt")
+        .write_str("with space.txt\n\nplain.txt\n")
+        .unwrap();
+
+    // The listed files follow the operands
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .current_dir(temp.path())
+        .args(["-n", "--files-from", "list.txt", "plain.txt"])
+        .assert()
+        .success()
+        .stdout("     1\tplain\n     2\tspaced\n     3\tplain\n");
+}
+
+#[test]
+fn test_files_from_null_separated_stdin() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("new\nline.txt").write_str("newline\n").unwrap();
+    temp.child("other.txt").write_str("other\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .current_dir(temp.path())
+        .args(["--files-from", "-", "--null"])
+        .write_stdin("new\nline.txt\0other.txt\0")
+        .assert()
+        .success()
+        .stdout("newline\nother\n");
+}
+
+#[test]
+fn test_files_from_missing_list() {
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--files-from", "missing_list.txt"])
+        .assert()
+        .failure()
+        .stderr("rustcat: missing_list.txt: No such file or directory\n");
+}
+
+#[test]
+fn test_header_box_and_separator() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("main.rs").write_str("fn main() {}\n").unwrap();
+    temp.child("notes.txt").write_str("no newline").unwrap();
+    let rule = |junction: char| format!("{}{}{}\n", "─".repeat(7), junction, "─".repeat(72));
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .current_dir(temp.path())
+        .args(["--header", "--separator=~~~", "--decorations=always"])
+        .args(["main.rs", "notes.txt"])
+        .assert()
+        .success()
+        .stdout(format!(
+            "{top}       │ File: main.rs\n       │ Size: 13 B\n       │ Language: Rust\n{middle}\
+             fn main() {{}}\n{bottom}~~~\n\
+             {top}       │ File: notes.txt\n       │ Size: 10 B\n       │ Language: Plain Text\n{middle}\
+             no newline\n{bottom}",
+            top = rule('┬'),
+            middle = rule('┼'),
+            bottom = rule('┴'),
+        ));
+}
+
+#[test]
+fn test_header_language_is_not_guessed() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("README").write_str("fn main() {}\n").unwrap();
+    temp.child("Makefile.local").write_str("all:\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .current_dir(temp.path())
+        .args([
+            "--header",
+            "--decorations=always",
+            "README",
+            "Makefile.local",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Language: Rust").not())
+        .stdout(predicate::str::contains("Language: Plain Text").count(2));
+}
+
+#[test]
+fn test_separator_after_file_without_trailing_newline() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("a.txt").write_str("no newline").unwrap();
+    temp.child("b.txt").write_str("b\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .current_dir(temp.path())
+        .args(["--separator=~~~", "--decorations=always", "a.txt", "b.txt"])
+        .assert()
+        .success()
+        .stdout("no newline\n~~~\nb\n");
+}
+
+#[test]
+fn test_decorations_are_suppressed_when_piped() {
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--header", "--separator=~~~", "test_data/large_test_1.txt"])
+        .arg("test_data/large_test_2.txt")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("~~~").not())
+        .stdout(predicate::str::contains("File:").not());
+}
+
+#[test]
+fn test_number_per_file() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("a.txt").write_str("a1\na2\n").unwrap();
+    temp.child("b.txt").write_str("b1\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .current_dir(temp.path())
+        .args(["-n", "--number-per-file", "a.txt", "b.txt"])
+        .assert()
+        .success()
+        .stdout("     1\ta1\n     2\ta2\n     1\tb1\n");
+}
+
+#[test]
+fn test_default_number_style_matches_cat() {
+    compare_rustcat_and_cat_for_linux(&vec!["--number-style=a"], &vec!["-n"]);
+    compare_rustcat_and_cat_for_linux(&vec!["--number-style=t"], &vec!["-b"]);
+    compare_rustcat_and_cat_for_linux(
+        &vec![
+            "-n",
+            "--number-width=6",
+            "--number-format=rn",
+            "--starting-line-number=1",
+            "--line-increment=1",
+        ],
+        &vec!["-n"],
+    );
+}
+
+#[test]
+fn test_number_width_format_and_separator() {
+    let file = assert_fs::NamedTempFile::new("numbers.txt").unwrap();
+    file.write_str("a\nb\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-n", "--number-width=3", "--number-format=rz"])
+        .args(["--number-separator", ": "])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("001: a\n002: b\n");
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-n", "--number-width=3", "--number-format=ln"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("1  \ta\n2  \tb\n");
+}
+
+#[test]
+fn test_starting_line_number_and_increment() {
+    let file = assert_fs::NamedTempFile::new("numbers.txt").unwrap();
+    file.write_str("a\nb\nc\nd\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-n", "--starting-line-number=10", "--line-increment=5"])
+        .args(["--lines", "2:3"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("    15\tb\n    20\tc\n");
+}
+
+#[test]
+fn test_number_style_regex() {
+    let file = assert_fs::NamedTempFile::new("numbers.txt").unwrap();
+    file.write_str("fn a\nlet x\nfn b\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--number-style", "p^fn"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("     1\tfn a\nlet x\n     2\tfn b\n");
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--number-style", "p("])
+        .arg(file.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("regex parse error"));
+}
+
+#[test]
+fn test_squeeze_blank_matches_cat_on_whitespace_lines() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let file = temp.child("blank.txt");
+    file.write_str("a\n\n\n\n  \n \n\nb\n\t\n\n").unwrap();
+
+    for args in [vec!["-s"], vec!["-b"], vec!["-s", "-b"], vec!["-s", "-n"]] {
+        let cat_output = Command::new("cat")
+            .args(&args)
+            .arg(file.path())
+            .unwrap()
+            .stdout;
+        let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+        rustcat_cmd
+            .args(&args)
+            .arg(file.path())
+            .assert()
+            .success()
+            .stdout(cat_output);
+    }
+}
+
+#[test]
+fn test_squeeze_blank_keeps_up_to_n_lines() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let file = temp.child("blank.txt");
+    file.write_str("a\n\n\n\n  \n \n\nb\n\t\n\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .arg("--squeeze-blank=2")
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("a\n\n\n  \n \n\nb\n\t\n\n");
+}
+
+#[test]
+fn test_whitespace_is_blank() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let file = temp.child("blank.txt");
+    file.write_str("a\n\n\n\n  \n \n\nb\n\t\n\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-s", "-b", "--whitespace-is-blank"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("     1\ta\n\n     2\tb\n\t\n");
+}
+
+#[test]
+fn test_crlf_line_endings_match_cat() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let file = temp.child("crlf.txt");
+    file.write_str("a\r\nb\rc\n\r\nd\r").unwrap();
+
+    for arg in ["-E", "-v", "-A", "-e", "-n", "-s"] {
+        let cat_output = Command::new("cat")
+            .arg(arg)
+            .arg(file.path())
+            .unwrap()
+            .stdout;
+        let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+        rustcat_cmd
+            .arg(arg)
+            .arg(file.path())
+            .assert()
+            .success()
+            .stdout(cat_output);
+    }
+}
+
+#[test]
+fn test_eol_converts_line_endings() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let file = temp.child("crlf.txt");
+    file.write_str("a\r\nb\rc\n\r\nd\r").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .arg("--eol=lf")
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("a\nb\rc\n\nd\r");
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .arg("--eol=crlf")
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("a\r\nb\rc\r\n\r\nd\r");
+}
+
+#[test]
+fn test_mixed_line_endings_warning() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let file = temp.child("crlf.txt");
+    file.write_str("a\r\nb\rc\n\r\nd\r").unwrap();
+    temp.child("unix.txt").write_str("a\nb\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .arg(file.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "crlf.txt: warning: mixed line endings (2 CRLF, 1 LF)",
+        ));
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .arg(temp.child("unix.txt").path())
+        .assert()
+        .success()
+        .stderr("");
+}
+
+#[test]
+fn test_null_data_records() {
+    let file = assert_fs::NamedTempFile::new("records.bin").unwrap();
+    file.write_binary(b"a b\0\0\0multi\nline\0tail").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--null-data", "-n", "-E", "-s"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout(&b"     1\ta b$\0     2\t$\0     3\tmulti\nline$\0     4\ttail"[..]);
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-0", "-n", "--tail", "2"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout(&b"     4\tmulti\nline\0     5\ttail"[..]);
+}
+
+#[test]
+fn test_record_separator() {
+    let file = assert_fs::NamedTempFile::new("records.txt").unwrap();
+    file.write_str("ab;cd;eb;").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--record-separator", ";", "-n", "-f", "b"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("     1\ta\x1b[33mb\x1b[0m;     3\te\x1b[33mb\x1b[0m;");
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--record-separator", ";;"])
+        .arg(file.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid record separator"));
+}
+
+#[test]
+fn test_ensure_newline() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("a.txt").write_str("one\ntwo").unwrap();
+    temp.child("b.txt").write_str("three\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .current_dir(temp.path())
+        .args(["a.txt", "b.txt"])
+        .assert()
+        .success()
+        .stdout("one\ntwothree\n");
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .current_dir(temp.path())
+        .args(["--ensure-newline", "a.txt", "b.txt", "a.txt"])
+        .assert()
+        .success()
+        .stdout("one\ntwo\nthree\none\ntwo\n");
+}
+
+#[test]
+fn test_mark_missing_newline() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    temp.child("a.txt").write_str("one\ntwo").unwrap();
+    temp.child("b.txt").write_str("three\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .current_dir(temp.path())
+        .args(["--mark-missing-newline", "-E", "a.txt", "b.txt"])
+        .assert()
+        .success()
+        .stdout("one$\ntwo\n\\ No newline at end of file\nthree$\n");
+
+    // The unterminated line is not displayed, so nothing is marked
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .current_dir(temp.path())
+        .args(["--mark-missing-newline", "--lines", "1", "a.txt"])
+        .assert()
+        .success()
+        .stdout("one\n");
+}
+
+#[test]
+fn test_tabs_expand_like_expand() {
+    let file = assert_fs::NamedTempFile::new("tabs.txt").unwrap();
+    file.write_str("a\tb\nab\tc\td\n\tz\n").unwrap();
+
+    for stops in ["8", "4", "2,5"] {
+        let expand_output = Command::new("expand")
+            .args(["-t", stops])
+            .arg(file.path())
+            .unwrap()
+            .stdout;
+        let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+        rustcat_cmd
+            .args(["--tabs", stops])
+            .arg(file.path())
+            .assert()
+            .success()
+            .stdout(expand_output);
+    }
+}
+
+#[test]
+fn test_tabs_count_display_width_after_gutter() {
+    let file = assert_fs::NamedTempFile::new("tabs.txt").unwrap();
+    file.write_str("日本\tx\n").unwrap();
+
+    // The wide characters take four columns, and the gutter is not counted
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-n", "--tabs", "6"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("     1\t日本  x\n");
+}
+
+#[test]
+fn test_tab_arrow() {
+    let file = assert_fs::NamedTempFile::new("tabs.txt").unwrap();
+    file.write_str("ab\tc\n\tz\n").unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--tab-arrow", "--tabs", "4"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("ab→ c\n→   z\n");
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--tabs", "4,2"])
+        .arg(file.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("increasing order"));
+}
+
+#[test]
+fn test_show_whitespace() {
+    let file = assert_fs::NamedTempFile::new("config.yaml").unwrap();
+    file.write_str("key: a b  \nnb:\u{a0}x\u{2003}y\n\tz\t \n")
+        .unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--show-whitespace", "-T"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("key:·a·b\x1b[41m··\x1b[0m\nnb:⍽x␣y\n^Iz\x1b[41m^I·\x1b[0m\n");
+}
+
+#[test]
+fn test_show_unicode() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let file = temp.child("trojan.rs");
+    file.write_str(concat!(
+        "if access_level != \"user\u{202e} \u{2066}// Check if admin\u{2069} \u{2066}\" {\n",
+        "let a\u{200b}b = 1;\n",
+    ))
+    .unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .arg("--show-unicode")
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout(concat!(
+            "if access_level != \"user\x1b[7m<U+202E>\x1b[27m \x1b[7m<U+2066>\x1b[27m",
+            "// Check if admin\x1b[7m<U+2069>\x1b[27m \x1b[7m<U+2066>\x1b[27m\" {\n",
+            "let a\x1b[7m<U+200B>\x1b[27mb = 1;\n",
+        ));
+}
+
+#[test]
+fn test_show_unicode_homoglyphs() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    // A Cyrillic `а` hides in the identifier, while the Russian string is written in one script
+    let file = temp.child("login.rs");
+    file.write_str("let p\u{430}ssword = \"\u{43f}\u{430}\u{440}\u{43e}\u{43b}\u{44c}\";\n")
+        .unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .arg("--show-unicode")
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("let p\x1b[7m<U+0430>\x1b[27mssword = \"\u{43f}\u{430}\u{440}\u{43e}\u{43b}\u{44c}\";\n");
+
+    // The whole word is considered even when a search match splits it
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--show-unicode", "--search", "ssword"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout(concat!(
+            "let p\x1b[7m<U+0430>\x1b[27m\x1b[33mssword\x1b[0m = ",
+            "\"\u{43f}\u{430}\u{440}\u{43e}\u{43b}\u{44c}\";\n",
+        ));
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["--show-unicode", "--search", "p\u{430}", "--only-matching"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout("p\x1b[7m<U+0430>\x1b[27m\n");
+}
+
+#[test]
+fn test_check_bidi() {
+    let temp = assert_fs::TempDir::new().unwrap();
+    let file = temp.child("trojan.rs");
+    file.write_str(concat!(
+        "if access_level != \"user\u{202e} \u{2066}// Check if admin\u{2069} \u{2066}\" {\n",
+        "let a\u{200b}b = 1;\n",
+    ))
+    .unwrap();
+    // Zero-width characters are invisible, but do not reorder the text
+    temp.child("clean.rs")
+        .write_str("let a\u{200b}b = 1;\n")
+        .unwrap();
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .arg("--check-bidi")
+        .arg(file.path())
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "trojan.rs: bidirectional control character U+202E at byte 24",
+        ))
+        .stderr(predicate::str::contains("U+2069 at byte 48"));
+
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .arg("--check-bidi")
+        .arg(temp.child("clean.rs").path())
+        .assert()
+        .success()
+        .stderr("");
+}
+
+#[test]
+fn test_nonprinting_styles() {
+    let file = assert_fs::NamedTempFile::new("control.txt").unwrap();
+    file.write_binary(b"a\x00b\x1b[0m\tc\x7f\r\n").unwrap();
+
+    let cat_output = Command::new("cat")
+        .arg("-A")
+        .arg(file.path())
+        .unwrap()
+        .stdout;
+    let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+    rustcat_cmd
+        .args(["-A", "--nonprinting-style=caret"])
+        .arg(file.path())
+        .assert()
+        .success()
+        .stdout(cat_output);
+
+    for (style, expected) in [
+        ("picture", "a␀b␛[0m␉c␡␍$\n"),
+        ("hex", "a\\x00b\\x1b[0m\\x09c\\x7f\\x0d$\n"),
+        ("escape", "a\\0b\\e[0m\\tc\\x7f\\r$\n"),
+    ] {
+        let mut rustcat_cmd = assert_cmd::Command::cargo_bin("rustcat").unwrap();
+        rustcat_cmd
+            .arg("-A")
+            .arg(format!("--nonprinting-style={}", style))
+            .arg(file.path())
+            .assert()
+            .success()
+            .stdout(expected);
+    }
+}